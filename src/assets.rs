@@ -0,0 +1,158 @@
+use crate::error::SiteError;
+use crate::minify::minify_asset;
+use crate::output::OutputSink;
+use crate::theme::overlay_files;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use miette::Result;
+use serde::Serialize;
+use sha2::{Digest, Sha384};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tera::{Function, Value};
+
+/// STATIC_URI is where the contents of the static directory are served from.
+pub static STATIC_URI: &str = "/static";
+
+/// Asset is a single fingerprinted file from the static directory.
+#[derive(Serialize, Debug, Clone)]
+pub struct Asset {
+    /// url is the fingerprinted, immutable URL of the asset.
+    pub url: String,
+    /// integrity is the Subresource Integrity value for the asset.
+    pub integrity: String,
+}
+
+/// AssetManifest maps paths relative to the static directory
+/// (e.g. "css/site.css") to their fingerprinted [Asset](crate::assets::Asset).
+#[derive(Debug, Clone, Default)]
+pub struct AssetManifest {
+    assets: HashMap<String, Asset>,
+}
+
+/// walk_files returns every file under `dir`, recursively.
+pub fn walk_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).map_err(SiteError::from)? {
+        let path = entry.map_err(SiteError::from)?.path();
+        if path.is_dir() {
+            files.extend(walk_files(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// fingerprinted inserts `hash` before the extension of `rel_path`,
+/// i.e. "css/site.css" becomes "css/site.<hash>.css".
+fn fingerprinted(rel_path: &str, hash: &str) -> String {
+    let (dir, file) = match rel_path.rsplit_once('/') {
+        Some((dir, file)) => (format!("{}/", dir), file),
+        None => (String::new(), rel_path),
+    };
+    match file.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{}{}.{}.{}", dir, stem, hash, ext),
+        _ => format!("{}{}.{}", dir, file, hash),
+    }
+}
+
+impl AssetManifest {
+    /// build writes every file in `static_dirs` to the sink under `static/`, both
+    /// under its original name and under a fingerprinted name, and records the result.
+    /// Files in later directories override files with the same path in earlier ones,
+    /// and `generated` files override them all.
+    /// If `minify` is set, css and js are minified before they are hashed.
+    pub fn build(
+        static_dirs: &[PathBuf],
+        generated: Vec<(String, Vec<u8>)>,
+        sink: &mut dyn OutputSink,
+        minify: bool,
+    ) -> Result<Self> {
+        let mut manifest = AssetManifest::default();
+        for (rel_path, path) in overlay_files(static_dirs)? {
+            if generated.iter().any(|(name, _)| *name == rel_path) {
+                continue;
+            }
+            let contents = fs::read(&path).map_err(SiteError::from)?;
+            manifest.add(rel_path, contents, sink, minify)?;
+        }
+        for (rel_path, contents) in generated {
+            manifest.add(rel_path, contents, sink, minify)?;
+        }
+        Ok(manifest)
+    }
+
+    /// add writes a single file to the sink and records it.
+    fn add(
+        &mut self,
+        rel_path: String,
+        mut contents: Vec<u8>,
+        sink: &mut dyn OutputSink,
+        minify: bool,
+    ) -> Result<()> {
+        if minify {
+            contents = minify_asset(&rel_path, contents);
+        }
+
+        let digest = Sha384::digest(&contents);
+        let hash: String = digest[..5].iter().map(|b| format!("{:02x}", b)).collect();
+        let hashed_rel_path = fingerprinted(&rel_path, &hash);
+
+        for name in [&rel_path, &hashed_rel_path] {
+            sink.write(&Path::new("static").join(name), &contents)?;
+        }
+
+        self.assets.insert(
+            rel_path,
+            Asset {
+                url: format!("{}/{}", STATIC_URI, hashed_rel_path),
+                integrity: format!("sha384-{}", BASE64.encode(digest)),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn get(&self, rel_path: &str) -> Option<&Asset> {
+        self.assets.get(rel_path)
+    }
+}
+
+/// AssetManifest is registered as the `asset_url` Tera function:
+///
+/// {% set css = asset_url(path="css/site.css") %}
+/// <link rel="stylesheet" href="{{ css.url }}" integrity="{{ css.integrity }}">
+impl Function for AssetManifest {
+    fn call(&self, args: &HashMap<String, Value>) -> tera::Result<Value> {
+        let path = args
+            .get("path")
+            .and_then(Value::as_str)
+            .ok_or_else(|| tera::Error::msg("asset_url requires a `path` string argument"))?;
+        let asset = self.get(path.trim_start_matches('/')).ok_or_else(|| {
+            tera::Error::msg(format!("asset_url: unknown static asset `{}`", path))
+        })?;
+        Ok(tera::to_value(asset)?)
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprints_before_extension() {
+        assert_eq!(fingerprinted("css/site.css", "abc"), "css/site.abc.css");
+        assert_eq!(
+            fingerprinted("js/highlight-9.14.1.min.js", "abc"),
+            "js/highlight-9.14.1.min.abc.js"
+        );
+        assert_eq!(fingerprinted("LICENSE", "abc"), "LICENSE.abc");
+        assert_eq!(fingerprinted(".htaccess", "abc"), ".htaccess.abc");
+    }
+}
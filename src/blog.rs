@@ -2,6 +2,7 @@ use crate::error::SiteError;
 use chrono::NaiveDate;
 use miette::{Diagnostic, Result};
 use select::document::Document;
+use select::node::Node;
 use select::predicate::{Attr, Class, Name};
 use serde::Serialize;
 use std::collections::HashMap;
@@ -37,10 +38,14 @@ pub struct OrgModeHtml {
     pub html: String,
     pub slug: String,
     pub footnotes: Vec<String>,
+    /// cover is the first image in the article, used for link previews.
+    pub cover: Option<String>,
+    /// tags come from the org-mode `#+KEYWORDS:` line, if any.
+    pub tags: Vec<String>,
 }
 
-fn get_html_files(base: &str) -> Result<Vec<PathBuf>> {
-    let base = PathBuf::from(base);
+fn get_html_files(base: &Path) -> Result<Vec<PathBuf>> {
+    let base = base.to_path_buf();
     if !base.is_dir() {
         return Err(SiteError::NotADirectory(base).into());
     }
@@ -65,6 +70,7 @@ fn get_html_files(base: &str) -> Result<Vec<PathBuf>> {
     Ok(html_files)
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Error, Diagnostic, PartialEq)]
 pub enum ParsingError {
     #[error("Cannot find TOC in {0}")]
@@ -88,16 +94,45 @@ pub enum ParsingError {
     #[error("Cannot make slug for {0}")]
     #[diagnostic(code(app::parsing::cannot_make_slug))]
     CannotMakeSlug(PathBuf),
+    #[error("Invalid front matter in {0}: {1}")]
+    #[diagnostic(code(app::parsing::invalid_front_matter))]
+    InvalidFrontMatter(PathBuf, String),
 }
 
-pub fn get_html_contents(blog_file: &Path) -> Result<OrgModeHtml> {
-    let file_contents = fs::read_to_string(blog_file).map_err(SiteError::from)?;
-    let document = Document::from(file_contents.as_str());
+/// OrgExport is what blog articles and content pages share from an org-mode html export.
+pub(crate) struct OrgExport<'a> {
+    pub title: String,
+    pub toc: Option<Node<'a>>,
+    /// sections are the top level `outline-2` sections, never empty.
+    pub sections: Vec<Node<'a>>,
+    pub footnotes: Vec<String>,
+}
 
+/// parse_org_export finds the title, table of contents, sections and footnotes
+/// of the org-mode html export in `document`, read from `path`.
+pub(crate) fn parse_org_export<'a>(path: &Path, document: &'a Document) -> Result<OrgExport<'a>> {
     let title = document
         .find(Class("title"))
         .next()
-        .ok_or_else(|| ParsingError::CannotFindTitle(blog_file.to_path_buf()))?;
+        .ok_or_else(|| ParsingError::CannotFindTitle(path.to_path_buf()))?;
+
+    let sections: Vec<_> = document.find(Class("outline-2")).collect();
+    if sections.is_empty() {
+        return Err(ParsingError::CannotFindContents(path.to_path_buf()).into());
+    }
+
+    Ok(OrgExport {
+        title: title.text(),
+        toc: document.find(Attr("id", "text-table-of-contents")).next(),
+        sections,
+        footnotes: document.find(Class("footdef")).map(|x| x.html()).collect(),
+    })
+}
+
+pub fn get_html_contents(blog_file: &Path) -> Result<OrgModeHtml> {
+    let file_contents = fs::read_to_string(blog_file).map_err(SiteError::from)?;
+    let document = Document::from(file_contents.as_str());
+    let export = parse_org_export(blog_file, &document)?;
 
     let date_string = document
         .find(Class("timestamp"))
@@ -110,15 +145,11 @@ pub fn get_html_contents(blog_file: &Path) -> Result<OrgModeHtml> {
 
     let pub_date: String = date.format("%a, %d %b %Y 1:01:00 EST").to_string();
 
-    let toc = document
-        .find(Attr("id", "text-table-of-contents"))
-        .next()
+    let toc = export
+        .toc
         .ok_or_else(|| ParsingError::CannotFindToc(blog_file.to_path_buf()))?;
 
-    let html = document
-        .find(Class("outline-2"))
-        .next()
-        .ok_or_else(|| ParsingError::CannotFindContents(blog_file.to_path_buf()))?;
+    let html = export.sections[0];
 
     // The first paragraph is likely a good enough description.
     let desc = html
@@ -134,24 +165,39 @@ pub fn get_html_contents(blog_file: &Path) -> Result<OrgModeHtml> {
         .ok_or_else(|| ParsingError::CannotMakeSlug(blog_file.to_path_buf()))?
         .replace(".html", "");
 
-    let footnotes = document.find(Class("footdef")).map(|x| x.html()).collect();
+    let cover = html
+        .find(Name("img"))
+        .filter_map(|img| img.attr("src"))
+        .next()
+        .map(str::to_owned);
+
+    let tags = document
+        .find(Attr("name", "keywords"))
+        .filter_map(|meta| meta.attr("content"))
+        .flat_map(|keywords| keywords.split(','))
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_owned)
+        .collect();
 
     println!("Successfully parsed {:?}", blog_file);
 
     Ok(OrgModeHtml {
-        title: title.text(),
+        title: export.title,
         date,
         pub_date,
         toc: toc.html(),
         desc: desc.text(),
         html: html.html(),
         slug,
-        footnotes,
+        footnotes: export.footnotes,
+        cover,
+        tags,
     })
 }
 
-pub fn get_org_mode_files(blog_root: &str) -> Result<Vec<OrgModeHtml>> {
-    let org_files = get_html_files(blog_root)?;
+pub fn get_org_mode_files(blog_root: impl AsRef<Path>) -> Result<Vec<OrgModeHtml>> {
+    let org_files = get_html_files(blog_root.as_ref())?;
     let mut html_success: Vec<OrgModeHtml> = Vec::new();
     for html_file in org_files {
         match get_html_contents(&html_file) {
@@ -159,11 +205,11 @@ pub fn get_org_mode_files(blog_root: &str) -> Result<Vec<OrgModeHtml>> {
             Err(e) => eprintln!("Failed to parse file {:?}: {}", html_file, e),
         }
     }
-    html_success.sort_by(|a, b| b.date.cmp(&a.date));
+    html_success.sort_by_key(|h| std::cmp::Reverse(h.date));
     Ok(html_success)
 }
 
-pub fn get_org_blog(blog_root: &str) -> Result<OrgBlog> {
+pub fn get_org_blog(blog_root: impl AsRef<Path>) -> Result<OrgBlog> {
     let blog_files = get_org_mode_files(blog_root)?;
     let html: HashMap<Slug, OrgModeHtml> = blog_files
         .clone()
@@ -0,0 +1,199 @@
+use crate::assets::AssetManifest;
+use crate::assets::walk_files;
+use crate::blog::get_org_blog;
+use crate::colors::COLORS_STYLESHEET;
+use crate::config::{SITE_CONFIG, SiteConfig};
+use crate::context::{BLOG_ROOT, PAGES_ROOT, PICS_ROOT};
+use crate::error::SiteError;
+use crate::output::OutputSink;
+use crate::pages::get_content_pages;
+use crate::pics::{get_pics_gallery, tag_url};
+use crate::precompress::PrecompressSink;
+use crate::routes::generate_site;
+use crate::theme::{TEMPLATES_DIR, THEMES_DIR, load_templates, theme_dir};
+use miette::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tera::Tera;
+
+/// STATIC_DIR is the default relative path to the static files.
+pub static STATIC_DIR: &str = "static";
+
+/// SiteBuilder generates the whole site from its content roots, templates and
+/// static files into an [OutputSink](crate::output::OutputSink).
+///
+/// # Example
+///
+/// let mut sink = DirSink::new("public");
+/// SiteBuilder::new(&mut sink).minify(true).build()?;
+pub struct SiteBuilder<'a> {
+    sink: &'a mut dyn OutputSink,
+    tera: Tera,
+    config: PathBuf,
+    blog_root: PathBuf,
+    pages_root: PathBuf,
+    pics_root: PathBuf,
+    static_dir: PathBuf,
+    templates_dir: PathBuf,
+    themes_dir: PathBuf,
+    extra_paths: Vec<PathBuf>,
+    minify: bool,
+    precompress_cache: Option<PathBuf>,
+}
+
+impl<'a> SiteBuilder<'a> {
+    /// new creates a builder which renders the site into `sink`,
+    /// reading the pages from [SITE_CONFIG](crate::config::SITE_CONFIG) and content from [BLOG_ROOT](crate::context::BLOG_ROOT),
+    /// [PAGES_ROOT](crate::context::PAGES_ROOT),
+    /// [PICS_ROOT](crate::context::PICS_ROOT), [TEMPLATES_DIR](crate::theme::TEMPLATES_DIR)
+    /// and [STATIC_DIR](crate::builder::STATIC_DIR), on top of the configured theme.
+    pub fn new(sink: &'a mut dyn OutputSink) -> Self {
+        SiteBuilder {
+            sink,
+            tera: Tera::default(),
+            config: SITE_CONFIG.into(),
+            blog_root: BLOG_ROOT.into(),
+            pages_root: PAGES_ROOT.into(),
+            pics_root: PICS_ROOT.into(),
+            static_dir: STATIC_DIR.into(),
+            templates_dir: TEMPLATES_DIR.into(),
+            themes_dir: THEMES_DIR.into(),
+            extra_paths: Vec::new(),
+            minify: false,
+            precompress_cache: None,
+        }
+    }
+
+    /// tera sets the [Tera] instance the templates are merged into, so embedders can
+    /// register their own filters, functions and templates. The theme's and the site's
+    /// templates win over its templates of the same name.
+    pub fn tera(mut self, tera: Tera) -> Self {
+        self.tera = tera;
+        self
+    }
+
+    /// config sets the path of the [SiteConfig](crate::config::SiteConfig).
+    pub fn config(mut self, config: impl Into<PathBuf>) -> Self {
+        self.config = config.into();
+        self
+    }
+
+    pub fn blog_root(mut self, blog_root: impl Into<PathBuf>) -> Self {
+        self.blog_root = blog_root.into();
+        self
+    }
+
+    pub fn pages_root(mut self, pages_root: impl Into<PathBuf>) -> Self {
+        self.pages_root = pages_root.into();
+        self
+    }
+
+    pub fn pics_root(mut self, pics_root: impl Into<PathBuf>) -> Self {
+        self.pics_root = pics_root.into();
+        self
+    }
+
+    pub fn static_dir(mut self, static_dir: impl Into<PathBuf>) -> Self {
+        self.static_dir = static_dir.into();
+        self
+    }
+
+    pub fn templates_dir(mut self, templates_dir: impl Into<PathBuf>) -> Self {
+        self.templates_dir = templates_dir.into();
+        self
+    }
+
+    /// themes_dir sets the directory the configured theme is looked up in.
+    pub fn themes_dir(mut self, themes_dir: impl Into<PathBuf>) -> Self {
+        self.themes_dir = themes_dir.into();
+        self
+    }
+
+    /// extra_path copies a file or directory into the root of the output.
+    pub fn extra_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.extra_paths.push(path.into());
+        self
+    }
+
+    /// minify minifies generated html pages and static css/js.
+    pub fn minify(mut self, minify: bool) -> Self {
+        self.minify = minify;
+        self
+    }
+
+    /// precompress writes .gz and .br siblings for compressible output files,
+    /// caching compressed files in `cache_dir` across builds.
+    pub fn precompress(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.precompress_cache = Some(cache_dir.into());
+        self
+    }
+
+    /// build generates the site, replacing anything already in the sink.
+    pub fn build(self) -> Result<()> {
+        let config = SiteConfig::load(&self.config)?;
+
+        // The site's own templates and static files override the theme's.
+        let mut template_dirs = Vec::new();
+        let mut static_dirs = Vec::new();
+        if let Some(theme) = &config.theme {
+            let theme_dir = theme_dir(&self.themes_dir, theme)?;
+            println!("Using theme: {:?}", theme_dir);
+            template_dirs.push(theme_dir.join("templates"));
+            static_dirs.push(theme_dir.join("static"));
+        }
+        template_dirs.push(self.templates_dir.clone());
+        static_dirs.push(self.static_dir.clone());
+        let mut tera = load_templates(&template_dirs)?;
+        tera.extend(&self.tera).map_err(SiteError::from)?;
+
+        let mut precompressed;
+        let sink: &mut dyn OutputSink = match &self.precompress_cache {
+            Some(cache_dir) => {
+                precompressed = PrecompressSink::new(self.sink, cache_dir);
+                &mut precompressed
+            }
+            None => self.sink,
+        };
+        sink.begin()?;
+
+        // Copy and fingerprint static files
+        println!("Copying static files from: {:?}", static_dirs);
+        let colors = config.colors.stylesheet().into_bytes();
+        let generated = vec![(COLORS_STYLESHEET.to_owned(), colors)];
+        let assets = AssetManifest::build(&static_dirs, generated, sink, self.minify)?;
+        tera.register_function("asset_url", assets);
+        tera.register_filter("tag_url", tag_url);
+
+        // Copy extra paths
+        for path in &self.extra_paths {
+            println!("Copying extra path: {:?}", path);
+            let file_name = path
+                .file_name()
+                .ok_or_else(|| SiteError::FileNotFound(path.to_string_lossy().into_owned()))?;
+            if !path.is_dir() {
+                let contents = fs::read(path).map_err(SiteError::from)?;
+                sink.write(Path::new(file_name), &contents)?;
+                continue;
+            }
+            for file in walk_files(path)? {
+                let rel_path = file
+                    .strip_prefix(path)
+                    .expect("walk_files only returns children of path");
+                let contents = fs::read(&file).map_err(SiteError::from)?;
+                sink.write(&Path::new(file_name).join(rel_path), &contents)?;
+            }
+        }
+
+        let blog = get_org_blog(&self.blog_root)?;
+        let pages = get_content_pages(&self.pages_root)?;
+        let pics = get_pics_gallery(&self.pics_root)?;
+
+        println!("Generating site...");
+        generate_site(&tera, sink, &config, &blog, &pages, &pics, self.minify)?;
+
+        sink.finish()?;
+        println!("Site generation complete.");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,374 @@
+use exif::{Exif, In, Reader, Tag, Value};
+use serde::Serialize;
+use std::io::Cursor;
+
+/// CameraInfo is the camera metadata embedded in a photo's EXIF, formatted for display.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct CameraInfo {
+    /// camera is the make and model, i.e. "FUJIFILM X100V".
+    pub camera: Option<String>,
+    pub lens: Option<String>,
+    /// focal_length is i.e. "23 mm".
+    pub focal_length: Option<String>,
+    /// aperture is i.e. "f/2.8".
+    pub aperture: Option<String>,
+    /// exposure is i.e. "1/250 s".
+    pub exposure: Option<String>,
+    pub iso: Option<String>,
+    /// taken is when the shutter fired, i.e. "2026-04-10 09:30:00".
+    pub taken: Option<String>,
+    /// gps is where the photo was taken, if the camera recorded it.
+    pub gps: Option<GeoPoint>,
+}
+
+/// GeoPoint is a position in decimal degrees.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    /// new returns the point at `lat`, `lon`, if they're on the globe.
+    pub fn new(lat: f64, lon: f64) -> Option<Self> {
+        let on_globe = (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon);
+        on_globe.then_some(GeoPoint { lat, lon })
+    }
+
+    /// parse reads a `lat,lon` pair, i.e. "43.6426, -79.3871".
+    pub fn parse(text: &str) -> Option<Self> {
+        let (lat, lon) = text.split_once(',')?;
+        GeoPoint::new(lat.trim().parse().ok()?, lon.trim().parse().ok()?)
+    }
+}
+
+/// ascii returns the text of an ASCII EXIF field, if it isn't empty.
+fn ascii(exif: &Exif, tag: Tag) -> Option<String> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    let Value::Ascii(parts) = &field.value else {
+        return None;
+    };
+    let text = parts
+        .iter()
+        .map(|part| String::from_utf8_lossy(part).trim().to_owned())
+        .collect::<Vec<_>>()
+        .join(" ");
+    Some(text).filter(|text| !text.is_empty())
+}
+
+/// display returns the EXIF field formatted with its unit, i.e. "f/2.8".
+fn display(exif: &Exif, tag: Tag) -> Option<String> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    Some(field.display_value().with_unit(exif).to_string())
+}
+
+/// coordinate returns a GPS latitude or longitude in decimal degrees. EXIF stores them
+/// as unsigned degrees, minutes and seconds, with the hemisphere in `ref_tag`.
+fn coordinate(exif: &Exif, tag: Tag, ref_tag: Tag, negative_ref: &str) -> Option<f64> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    let Value::Rational(dms) = &field.value else {
+        return None;
+    };
+    let [degrees, minutes, seconds] = dms.get(..3)? else {
+        return None;
+    };
+    let value = degrees.to_f64() + minutes.to_f64() / 60.0 + seconds.to_f64() / 3600.0;
+    let sign = if ascii(exif, ref_tag).as_deref() == Some(negative_ref) {
+        -1.0
+    } else {
+        1.0
+    };
+    Some(sign * value).filter(|value| value.is_finite())
+}
+
+/// read_camera_info reads the camera metadata of the photo in `bytes`.
+/// Photos which can't be read, or have no camera metadata, have none.
+pub fn read_camera_info(bytes: &[u8]) -> Option<CameraInfo> {
+    let exif = Reader::new()
+        .read_from_container(&mut Cursor::new(bytes))
+        .ok()?;
+
+    let make = ascii(&exif, Tag::Make);
+    let model = ascii(&exif, Tag::Model);
+    // Models usually repeat the make, i.e. "Canon" and "Canon EOS R6".
+    let camera = match (make, model) {
+        (Some(make), Some(model)) if !model.starts_with(&make) => {
+            Some(format!("{} {}", make, model))
+        }
+        (make, model) => model.or(make),
+    };
+
+    let info = CameraInfo {
+        camera,
+        lens: ascii(&exif, Tag::LensModel),
+        focal_length: display(&exif, Tag::FocalLength),
+        aperture: display(&exif, Tag::FNumber),
+        exposure: display(&exif, Tag::ExposureTime),
+        iso: display(&exif, Tag::PhotographicSensitivity),
+        taken: display(&exif, Tag::DateTimeOriginal),
+        gps: coordinate(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, "S").and_then(|lat| {
+            let lon = coordinate(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, "W")?;
+            GeoPoint::new(lat, lon)
+        }),
+    };
+    Some(info).filter(|info| *info != CameraInfo::default())
+}
+
+/// GPS_INFO is the EXIF tag pointing at the GPS IFD.
+const GPS_INFO: u16 = 0x8825;
+
+/// scrub_gps_ifd empties the GPS IFD of the TIFF structure in `tiff` in place,
+/// zeroing its entries and the values they point at. Malformed EXIF is left alone.
+fn scrub_gps_ifd(tiff: &mut [u8]) -> Option<()> {
+    let big_endian = match tiff.get(..4)? {
+        b"MM\0*" => true,
+        b"II*\0" => false,
+        _ => return None,
+    };
+    let u16_at = |tiff: &[u8], at: usize| {
+        let bytes = tiff.get(at..at + 2)?.try_into().ok()?;
+        Some(usize::from(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }))
+    };
+    let u32_at = |tiff: &[u8], at: usize| {
+        let bytes = tiff.get(at..at + 4)?.try_into().ok()?;
+        let n = if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        };
+        usize::try_from(n).ok()
+    };
+
+    let ifd0 = u32_at(tiff, 4)?;
+    let gps = (0..u16_at(tiff, ifd0)?)
+        .map(|i| ifd0 + 2 + 12 * i)
+        .find(|&entry| u16_at(tiff, entry) == Some(usize::from(GPS_INFO)))
+        .and_then(|entry| u32_at(tiff, entry + 8))?;
+
+    let entries = u16_at(tiff, gps)?;
+    for entry in (0..entries).map(|i| gps + 2 + 12 * i) {
+        let size = match u16_at(tiff, entry + 2)? {
+            1 | 2 | 6 | 7 => 1,
+            3 | 8 => 2,
+            4 | 9 | 11 => 4,
+            5 | 10 | 12 => 8,
+            _ => 0,
+        } * u32_at(tiff, entry + 4)?;
+        // Values of up to 4 bytes are stored in the entry itself.
+        if size > 4 {
+            let offset = u32_at(tiff, entry + 8)?;
+            tiff.get_mut(offset..offset + size)?.fill(0);
+        }
+    }
+    // An empty IFD: no entries, followed by a zero next-IFD offset.
+    tiff.get_mut(gps..gps + 2 + 12 * entries + 4)?.fill(0);
+    Some(())
+}
+
+/// strip_location removes where a photo was taken from a JPEG before it's published:
+/// the EXIF GPS position is erased and XMP metadata, which can repeat it, is dropped.
+/// Everything else, like the orientation, is kept. Other formats are returned as is.
+pub fn strip_location(mut photo: Vec<u8>) -> Vec<u8> {
+    if !photo.starts_with(&[0xff, 0xd8]) {
+        return photo;
+    }
+    let mut xmp = Vec::new();
+    let mut at = 2;
+    // Metadata segments come before the start of scan (0xda).
+    while let Some(&[0xff, marker, hi, lo]) = photo.get(at..at + 4) {
+        if marker == 0xda {
+            break;
+        }
+        let end = at + 2 + usize::from(u16::from_be_bytes([hi, lo]));
+        let Some(segment) = photo.get_mut(at + 4..end) else {
+            break;
+        };
+        if marker == 0xe1 {
+            if segment.starts_with(b"Exif\0\0") {
+                scrub_gps_ifd(&mut segment[6..]);
+            } else if segment.starts_with(b"http://ns.adobe.com/") {
+                xmp.push(at..end);
+            }
+        }
+        at = end;
+    }
+    for range in xmp.into_iter().rev() {
+        photo.drain(range);
+    }
+    photo
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::experimental::Writer;
+    use exif::{Context, Field, Rational};
+    use image::{ImageFormat, RgbImage};
+
+    /// jpeg_with_exif encodes a small JPEG with `fields` in an APP1 segment.
+    fn jpeg_with_exif(fields: &[Field]) -> Vec<u8> {
+        let mut tiff = Cursor::new(Vec::new());
+        let mut writer = Writer::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        writer.write(&mut tiff, false).unwrap();
+        let payload = [b"Exif\0\0".as_slice(), tiff.get_ref()].concat();
+
+        let mut jpeg = Cursor::new(Vec::new());
+        RgbImage::new(8, 8)
+            .write_to(&mut jpeg, ImageFormat::Jpeg)
+            .unwrap();
+        let jpeg = jpeg.into_inner();
+        let len = (payload.len() + 2) as u16;
+        [
+            &jpeg[..2],
+            &[0xff, 0xe1],
+            &len.to_be_bytes(),
+            &payload,
+            &jpeg[2..],
+        ]
+        .concat()
+    }
+
+    fn field(tag: Tag, value: Value) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value,
+        }
+    }
+
+    #[test]
+    fn reads_camera_settings() {
+        let fields = [
+            field(Tag::Make, Value::Ascii(vec![b"Canon".to_vec()])),
+            field(Tag::Model, Value::Ascii(vec![b"Canon EOS R6".to_vec()])),
+            field(
+                Tag::FNumber,
+                Value::Rational(vec![Rational { num: 28, denom: 10 }]),
+            ),
+            field(
+                Tag::ExposureTime,
+                Value::Rational(vec![Rational { num: 1, denom: 250 }]),
+            ),
+            field(
+                Tag::FocalLength,
+                Value::Rational(vec![Rational { num: 50, denom: 1 }]),
+            ),
+            field(Tag::PhotographicSensitivity, Value::Short(vec![400])),
+            field(
+                Tag::DateTimeOriginal,
+                Value::Ascii(vec![b"2026:04:10 09:30:00".to_vec()]),
+            ),
+        ];
+        let info = read_camera_info(&jpeg_with_exif(&fields)).unwrap();
+        assert_eq!(info.camera.as_deref(), Some("Canon EOS R6"));
+        assert_eq!(info.lens, None);
+        assert_eq!(info.aperture.as_deref(), Some("f/2.8"));
+        assert_eq!(info.exposure.as_deref(), Some("1/250 s"));
+        assert_eq!(info.focal_length.as_deref(), Some("50 mm"));
+        assert_eq!(info.iso.as_deref(), Some("400"));
+        assert_eq!(info.taken.as_deref(), Some("2026-04-10 09:30:00"));
+        assert_eq!(info.gps, None);
+    }
+
+    #[test]
+    fn reads_gps_position() {
+        let dms = |d, m, s| {
+            Value::Rational(vec![
+                Rational { num: d, denom: 1 },
+                Rational { num: m, denom: 1 },
+                Rational { num: s, denom: 100 },
+            ])
+        };
+        let fields = [
+            field(Tag::GPSLatitudeRef, Value::Ascii(vec![b"N".to_vec()])),
+            field(Tag::GPSLatitude, dms(43, 38, 3600)),
+            field(Tag::GPSLongitudeRef, Value::Ascii(vec![b"W".to_vec()])),
+            field(Tag::GPSLongitude, dms(79, 23, 1800)),
+        ];
+        let gps = read_camera_info(&jpeg_with_exif(&fields))
+            .unwrap()
+            .gps
+            .unwrap();
+        assert!((gps.lat - 43.643333).abs() < 1e-5, "{:?}", gps);
+        assert!((gps.lon + 79.388333).abs() < 1e-5, "{:?}", gps);
+    }
+
+    #[test]
+    fn strips_location_from_published_copies() {
+        let fields = [
+            field(Tag::Make, Value::Ascii(vec![b"Canon".to_vec()])),
+            field(Tag::Orientation, Value::Short(vec![6])),
+            field(Tag::GPSLatitudeRef, Value::Ascii(vec![b"N".to_vec()])),
+            field(
+                Tag::GPSLatitude,
+                Value::Rational(vec![Rational { num: 43, denom: 1 }; 3]),
+            ),
+        ];
+        let jpeg = jpeg_with_exif(&fields);
+        let xmp = b"http://ns.adobe.com/xap/1.0/\0<x:xmpmeta exif:GPSLatitude=\"43,38N\"/>";
+        let len = (xmp.len() + 2) as u16;
+        let jpeg = [
+            &jpeg[..2],
+            &[0xff, 0xe1],
+            &len.to_be_bytes(),
+            xmp,
+            &jpeg[2..],
+        ]
+        .concat();
+
+        let gps_fields = |jpeg: &[u8]| {
+            let exif = Reader::new()
+                .read_from_container(&mut Cursor::new(jpeg))
+                .unwrap();
+            exif.fields()
+                .filter(|f| f.tag.context() == Context::Gps)
+                .count()
+        };
+        assert_eq!(gps_fields(&jpeg), 2);
+
+        let stripped = strip_location(jpeg.clone());
+        assert_eq!(gps_fields(&stripped), 0);
+        assert_eq!(stripped.len(), jpeg.len() - xmp.len() - 4);
+        assert!(!stripped.windows(11).any(|w| w == b"GPSLatitude"));
+        let exif = Reader::new()
+            .read_from_container(&mut Cursor::new(&stripped))
+            .unwrap();
+        assert!(exif.get_field(Tag::Orientation, In::PRIMARY).is_some());
+        assert!(exif.get_field(Tag::Make, In::PRIMARY).is_some());
+        image::load_from_memory(&stripped).unwrap();
+
+        let png = b"\x89PNG\r\n\x1a\n".to_vec();
+        assert_eq!(strip_location(png.clone()), png);
+    }
+
+    #[test]
+    fn parses_geo_points() {
+        assert_eq!(
+            GeoPoint::parse(" 43.6426, -79.3871 "),
+            Some(GeoPoint {
+                lat: 43.6426,
+                lon: -79.3871
+            })
+        );
+        assert_eq!(GeoPoint::parse("43.6426"), None);
+        assert_eq!(GeoPoint::parse("91, 0"), None);
+        assert_eq!(GeoPoint::parse("north, west"), None);
+    }
+
+    #[test]
+    fn photos_without_exif_have_no_camera_info() {
+        let mut plain = Cursor::new(Vec::new());
+        RgbImage::new(8, 8)
+            .write_to(&mut plain, ImageFormat::Jpeg)
+            .unwrap();
+        assert_eq!(read_camera_info(plain.get_ref()), None);
+        assert_eq!(read_camera_info(b"not a jpeg"), None);
+    }
+}
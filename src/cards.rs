@@ -0,0 +1,331 @@
+use crate::blog::OrgModeHtml;
+use crate::error::SiteError;
+use crate::pics::PicSession;
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont, point};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, ImageFormat, RgbImage, Rgba, RgbaImage};
+use miette::Result;
+use std::fmt;
+use std::io::Cursor;
+
+/// CARD_FILENAME is the name of the preview image written next to each page.
+/// It's reserved: pics sessions can't publish a file with the same name.
+pub static CARD_FILENAME: &str = "_card.png";
+
+/// Open Graph recommends 1200x630 for large previews.
+const CARD_WIDTH: u32 = 1200;
+const CARD_HEIGHT: u32 = 630;
+const MARGIN: f32 = 72.0;
+
+/// Maximum number of photos in a session collage.
+pub const COLLAGE_PHOTOS: usize = 4;
+
+// Colours are borrowed from the bootstrap slate theme used by the site.
+const BACKGROUND: Rgba<u8> = Rgba([39, 43, 48, 255]);
+const FOREGROUND: Rgba<u8> = Rgba([233, 236, 239, 255]);
+const MUTED: Rgba<u8> = Rgba([170, 170, 170, 255]);
+const ACCENT: Rgba<u8> = Rgba([91, 186, 213, 255]);
+const BAND: Rgba<u8> = Rgba([39, 43, 48, 210]);
+
+/// The font is bundled so cards render the same everywhere, see fonts/LICENSE.
+static FONT_BYTES: &[u8] = include_bytes!("../fonts/DejaVuSansMono-Bold.ttf");
+
+fn font() -> Result<FontRef<'static>> {
+    Ok(FontRef::try_from_slice(FONT_BYTES).map_err(SiteError::from)?)
+}
+
+fn text_width(font: &FontRef, size: f32, text: &str) -> f32 {
+    let scaled = font.as_scaled(PxScale::from(size));
+    text.chars()
+        .map(|c| scaled.h_advance(scaled.glyph_id(c)))
+        .sum()
+}
+
+/// draw_text draws a single line of text with its top-left corner at (x, y).
+fn draw_text(
+    img: &mut RgbaImage,
+    font: &FontRef,
+    size: f32,
+    x: f32,
+    y: f32,
+    colour: Rgba<u8>,
+    text: &str,
+) {
+    let scale = PxScale::from(size);
+    let scaled = font.as_scaled(scale);
+    let mut caret = x;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        let glyph = id.with_scale_and_position(scale, point(caret, y + scaled.ascent()));
+        caret += scaled.h_advance(id);
+        let Some(outline) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outline.px_bounds();
+        outline.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i64 + gx as i64;
+            let py = bounds.min.y as i64 + gy as i64;
+            if px >= 0 && py >= 0 && (px as u32) < img.width() && (py as u32) < img.height() {
+                blend(img.get_pixel_mut(px as u32, py as u32), colour, coverage);
+            }
+        });
+    }
+}
+
+fn blend(pixel: &mut Rgba<u8>, colour: Rgba<u8>, coverage: f32) {
+    let alpha = coverage.clamp(0.0, 1.0) * (colour[3] as f32 / 255.0);
+    for i in 0..3 {
+        pixel[i] = (pixel[i] as f32 * (1.0 - alpha) + colour[i] as f32 * alpha).round() as u8;
+    }
+    pixel[3] = 255;
+}
+
+fn fill_rect(img: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, colour: Rgba<u8>) {
+    for py in y..(y + height).min(img.height()) {
+        for px in x..(x + width).min(img.width()) {
+            blend(img.get_pixel_mut(px, py), colour, 1.0);
+        }
+    }
+}
+
+/// wrap_text greedily wraps `text` to `max_width`, truncating with an
+/// ellipsis after `max_lines`.
+fn wrap_text(
+    font: &FontRef,
+    size: f32,
+    text: &str,
+    max_width: f32,
+    max_lines: usize,
+) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if current.is_empty() {
+            word.to_owned()
+        } else {
+            format!("{} {}", current, word)
+        };
+        if current.is_empty() || text_width(font, size, &candidate) <= max_width {
+            current = candidate;
+        } else {
+            lines.push(std::mem::replace(&mut current, word.to_owned()));
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        let last = lines.last_mut().unwrap();
+        while !last.is_empty() && text_width(font, size, &format!("{}…", last)) > max_width {
+            last.pop();
+        }
+        last.push('…');
+    }
+    lines
+}
+
+fn encode_png(img: &RgbaImage) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    img.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .map_err(SiteError::from)?;
+    Ok(bytes)
+}
+
+/// render_article_card renders the preview card for a blog article as a PNG.
+pub fn render_article_card(post: &OrgModeHtml, site_name: &str) -> Result<Vec<u8>> {
+    let font = font()?;
+    let mut img = RgbaImage::from_pixel(CARD_WIDTH, CARD_HEIGHT, BACKGROUND);
+    fill_rect(&mut img, 0, 0, 16, CARD_HEIGHT, ACCENT);
+
+    let max_width = CARD_WIDTH as f32 - 2.0 * MARGIN;
+    let mut y = MARGIN;
+    draw_text(
+        &mut img,
+        &font,
+        32.0,
+        MARGIN,
+        y,
+        MUTED,
+        &post.date.format("%Y-%m-%d").to_string(),
+    );
+    y += 72.0;
+    for line in wrap_text(&font, 64.0, &post.title, max_width, 4) {
+        draw_text(&mut img, &font, 64.0, MARGIN, y, FOREGROUND, &line);
+        y += 80.0;
+    }
+    if !post.tags.is_empty() {
+        let tags = post
+            .tags
+            .iter()
+            .map(|tag| format!("#{}", tag))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if let Some(line) = wrap_text(&font, 28.0, &tags, max_width, 1).first() {
+            draw_text(&mut img, &font, 28.0, MARGIN, y + 8.0, ACCENT, line);
+        }
+    }
+    draw_text(
+        &mut img,
+        &font,
+        32.0,
+        MARGIN,
+        CARD_HEIGHT as f32 - MARGIN - 32.0,
+        ACCENT,
+        site_name,
+    );
+    encode_png(&img)
+}
+
+/// collage_cells splits the card into one cell per photo, as (x, y, width, height).
+fn collage_cells(count: usize) -> Vec<(u32, u32, u32, u32)> {
+    let (w, h) = (CARD_WIDTH, CARD_HEIGHT);
+    match count {
+        1 => vec![(0, 0, w, h)],
+        2 => vec![(0, 0, w / 2, h), (w / 2, 0, w - w / 2, h)],
+        3 => vec![
+            (0, 0, w / 2, h),
+            (w / 2, 0, w - w / 2, h / 2),
+            (w / 2, h / 2, w - w / 2, h - h / 2),
+        ],
+        _ => vec![
+            (0, 0, w / 2, h / 2),
+            (w / 2, 0, w - w / 2, h / 2),
+            (0, h / 2, w / 2, h - h / 2),
+            (w / 2, h / 2, w - w / 2, h - h / 2),
+        ],
+    }
+}
+
+/// Collage is the photos of a session's preview card, cropped to their cells
+/// while the session is loaded so the card doesn't decode them again.
+#[derive(Clone, Default)]
+pub struct Collage {
+    /// cells are the photos in the order they're laid out, cover first.
+    pub(crate) cells: Vec<RgbImage>,
+}
+
+impl Collage {
+    /// from_photos crops the first [COLLAGE_PHOTOS](crate::cards::COLLAGE_PHOTOS)
+    /// of `photos` to their cells of the card.
+    pub fn from_photos(photos: &[DynamicImage]) -> Self {
+        let photos = &photos[..photos.len().min(COLLAGE_PHOTOS)];
+        let cells = photos
+            .iter()
+            .zip(collage_cells(photos.len()))
+            .map(|(photo, (_, _, w, h))| photo.resize_to_fill(w, h, FilterType::Triangle).to_rgb8())
+            .collect();
+        Collage { cells }
+    }
+}
+
+impl fmt::Debug for Collage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Collage")
+            .field("cells", &self.cells.len())
+            .finish()
+    }
+}
+
+/// collage_photo shrinks a decoded photo to the most pixels a collage cell can
+/// need, so a session only keeps small copies until its collage is cropped.
+pub fn collage_photo(photo: &DynamicImage) -> DynamicImage {
+    let scale = (f64::from(CARD_WIDTH) / f64::from(photo.width()))
+        .max(f64::from(CARD_HEIGHT) / f64::from(photo.height()));
+    if scale >= 1.0 {
+        return photo.clone();
+    }
+    let width = (f64::from(photo.width()) * scale).ceil() as u32;
+    let height = (f64::from(photo.height()) * scale).ceil() as u32;
+    photo.resize_exact(width, height, FilterType::Triangle)
+}
+
+/// render_session_card renders the preview card for a pics session as a PNG:
+/// its [Collage](crate::cards::Collage) with the session title on top.
+pub fn render_session_card(session: &PicSession, site_name: &str) -> Result<Vec<u8>> {
+    let font = font()?;
+    let mut img = RgbaImage::from_pixel(CARD_WIDTH, CARD_HEIGHT, BACKGROUND);
+
+    let cells = &session.collage.cells;
+    if cells.is_empty() {
+        fill_rect(&mut img, 0, 0, 16, CARD_HEIGHT, ACCENT);
+    }
+    for (cell, (x, y, _, _)) in cells.iter().zip(collage_cells(cells.len())) {
+        let cell = DynamicImage::ImageRgb8(cell.clone()).to_rgba8();
+        imageops::overlay(&mut img, &cell, x as i64, y as i64);
+    }
+
+    let band_height = 180;
+    fill_rect(
+        &mut img,
+        0,
+        CARD_HEIGHT - band_height,
+        CARD_WIDTH,
+        band_height,
+        BAND,
+    );
+    let max_width = CARD_WIDTH as f32 - 2.0 * MARGIN;
+    let top = (CARD_HEIGHT - band_height) as f32 + 32.0;
+    if let Some(title) = wrap_text(&font, 56.0, &session.title, max_width, 1).first() {
+        draw_text(&mut img, &font, 56.0, MARGIN, top, FOREGROUND, title);
+    }
+    let footer = format!("{} · {}", session.date.format("%Y-%m-%d"), site_name);
+    draw_text(&mut img, &font, 28.0, MARGIN, top + 80.0, MUTED, &footer);
+    encode_png(&img)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pics::Photo;
+    use chrono::NaiveDate;
+    use std::path::PathBuf;
+
+    fn decode(png: &[u8]) -> RgbaImage {
+        image::load_from_memory_with_format(png, ImageFormat::Png)
+            .unwrap()
+            .to_rgba8()
+    }
+
+    #[test]
+    fn wraps_and_truncates_long_titles() {
+        let font = font().unwrap();
+        let lines = wrap_text(&font, 64.0, &"word ".repeat(100), 600.0, 2);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].ends_with('…'));
+        assert!(lines.iter().all(|l| text_width(&font, 64.0, l) <= 600.0));
+    }
+
+    #[test]
+    fn session_card_is_a_collage_of_photos() {
+        let red = DynamicImage::ImageRgb8(RgbImage::from_pixel(64, 64, image::Rgb([255, 0, 0])));
+        let session = PicSession {
+            date: NaiveDate::from_ymd_opt(2026, 4, 10).unwrap(),
+            date_str: "2026-04-10".to_owned(),
+            slug: "2026-04-10".to_owned(),
+            dir: PathBuf::new(),
+            title: "First Roll".to_owned(),
+            description: String::new(),
+            photos: vec![Photo {
+                filename: "red.jpg".to_owned(),
+                ..Photo::default()
+            }],
+            front_matter: Default::default(),
+            collage: Collage::from_photos(&[collage_photo(&red)]),
+        };
+        let card = decode(&render_session_card(&session, "dpbriggs.ca").unwrap());
+        assert_eq!(card.dimensions(), (CARD_WIDTH, CARD_HEIGHT));
+        let top_left = card.get_pixel(100, 100);
+        assert!(top_left[0] > 200 && top_left[1] < 50, "{:?}", top_left);
+    }
+
+    #[test]
+    fn collage_photos_keep_enough_pixels_to_fill_the_card() {
+        let wide = DynamicImage::new_rgb8(4000, 1000);
+        let shrunk = collage_photo(&wide);
+        assert_eq!((shrunk.width(), shrunk.height()), (2520, CARD_HEIGHT));
+        let small = DynamicImage::new_rgb8(64, 64);
+        let small = collage_photo(&small);
+        assert_eq!((small.width(), small.height()), (64, 64));
+    }
+}
@@ -0,0 +1,233 @@
+use crate::error::SiteError;
+use miette::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// COLORS_STYLESHEET is the generated stylesheet holding the color scheme
+/// variables, relative to the static directory.
+pub static COLORS_STYLESHEET: &str = "css/colors.css";
+
+/// ColorMode decides which color schemes a site uses.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Auto follows the visitor's `prefers-color-scheme`.
+    #[default]
+    Auto,
+    Light,
+    Dark,
+}
+
+/// ColorsConfig is the `[colors]` table of the [SiteConfig](crate::config::SiteConfig).
+///
+/// # Example
+///
+/// [colors]
+/// mode = "auto"
+/// highlight = "solarized"
+///
+/// [colors.dark]
+/// stylesheets = ["css/bootstrap-slate.css"]
+///
+/// [colors.dark.vars]
+/// background = "#272b30"
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ColorsConfig {
+    #[serde(default)]
+    pub mode: ColorMode,
+    /// highlight is the highlight.js theme for code blocks. Each scheme uses its
+    /// own variant, i.e. "solarized" is `css/solarized-light.css` for the light
+    /// scheme and `css/solarized-dark.css` for the dark one.
+    pub highlight: Option<String>,
+    pub light: Option<ColorScheme>,
+    pub dark: Option<ColorScheme>,
+}
+
+/// ColorScheme is a single light or dark variant of the site.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ColorScheme {
+    /// stylesheets only apply with this scheme, relative to the static directory.
+    #[serde(default)]
+    pub stylesheets: Vec<String>,
+    /// vars are the css variables of this scheme, i.e. `background` is `--background`.
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>,
+}
+
+/// SchemeEntry is a [ColorScheme](crate::colors::ColorScheme) as linked from a page.
+#[derive(Serialize, Debug)]
+pub struct SchemeEntry {
+    /// name is either "light" or "dark".
+    pub name: &'static str,
+    /// media is the media query the scheme's stylesheets apply to.
+    pub media: &'static str,
+    /// stylesheets are the scheme's stylesheets, then its highlight.js theme.
+    pub stylesheets: Vec<String>,
+}
+
+/// is_css_name returns whether `name` can be used verbatim in a css
+/// identifier or file name, i.e. "code-background".
+fn is_css_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+impl ColorsConfig {
+    /// enabled returns the configured schemes the mode allows, light first.
+    fn enabled(&self) -> Vec<(&'static str, &ColorScheme)> {
+        [
+            ("light", &self.light, ColorMode::Light),
+            ("dark", &self.dark, ColorMode::Dark),
+        ]
+        .into_iter()
+        .filter(|(_, _, mode)| self.mode == ColorMode::Auto || self.mode == *mode)
+        .filter_map(|(name, scheme, _)| scheme.as_ref().map(|scheme| (name, scheme)))
+        .collect()
+    }
+
+    /// schemes returns the schemes pages link. With both schemes enabled, each
+    /// only applies when the visitor prefers it, otherwise the one scheme always applies.
+    pub fn schemes(&self) -> Vec<SchemeEntry> {
+        let enabled = self.enabled();
+        let both = enabled.len() > 1;
+        enabled
+            .into_iter()
+            .map(|(name, scheme)| {
+                let media = match (both, name) {
+                    (false, _) => "all",
+                    (true, "light") => "(prefers-color-scheme: light)",
+                    (true, _) => "(prefers-color-scheme: dark)",
+                };
+                let mut stylesheets = scheme.stylesheets.clone();
+                if let Some(highlight) = &self.highlight {
+                    stylesheets.push(format!("css/{}-{}.css", highlight, name));
+                }
+                SchemeEntry {
+                    name,
+                    media,
+                    stylesheets,
+                }
+            })
+            .collect()
+    }
+
+    /// stylesheet renders the [COLORS_STYLESHEET](crate::colors::COLORS_STYLESHEET).
+    /// The first scheme is the default, so browsers without `prefers-color-scheme`
+    /// still get a complete set of variables.
+    pub fn stylesheet(&self) -> String {
+        let enabled = self.enabled();
+        let color_scheme: Vec<&str> = enabled.iter().map(|(name, _)| *name).collect();
+        let mut css = String::from("/* Generated from [colors] in site.toml. */\n");
+        for (i, (name, scheme)) in enabled.iter().enumerate() {
+            let indent = if i == 0 { "" } else { "    " };
+            if i > 0 {
+                css.push_str(&format!("@media (prefers-color-scheme: {}) {{\n", name));
+            }
+            css.push_str(&format!("{}:root {{\n", indent));
+            if i == 0 {
+                css.push_str(&format!(
+                    "{}    color-scheme: {};\n",
+                    indent,
+                    color_scheme.join(" ")
+                ));
+            }
+            for (var, value) in &scheme.vars {
+                css.push_str(&format!("{}    --{}: {};\n", indent, var, value));
+            }
+            css.push_str(&format!("{}}}\n", indent));
+            if i > 0 {
+                css.push_str("}\n");
+            }
+        }
+        css
+    }
+
+    /// validate checks that names and values can't break out of the generated stylesheet.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(highlight) = self.highlight.as_deref().filter(|h| !is_css_name(h)) {
+            return Err(SiteError::InvalidColor(format!("highlight = {:?}", highlight)).into());
+        }
+        for (name, scheme) in self.enabled() {
+            for (var, value) in &scheme.vars {
+                let bad_value = value.is_empty() || value.contains([';', '{', '}', '<', '\n']);
+                if !is_css_name(var) || bad_value {
+                    return Err(SiteError::InvalidColor(format!(
+                        "colors.{}.vars.{} = {:?}",
+                        name, var, value
+                    ))
+                    .into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn colors(source: &str) -> ColorsConfig {
+        toml::from_str(source).unwrap()
+    }
+
+    const BOTH: &str = r##"
+        highlight = "solarized"
+
+        [light.vars]
+        background = "#fff"
+
+        [dark]
+        stylesheets = ["css/bootstrap-slate.css"]
+
+        [dark.vars]
+        background = "#272b30"
+        text = "#aaa"
+    "##;
+
+    #[test]
+    fn auto_mode_follows_the_preferred_scheme() {
+        let colors = colors(BOTH);
+        let schemes = colors.schemes();
+        assert_eq!(schemes[0].media, "(prefers-color-scheme: light)");
+        assert_eq!(schemes[0].stylesheets, ["css/solarized-light.css"]);
+        assert_eq!(schemes[1].media, "(prefers-color-scheme: dark)");
+        assert_eq!(
+            schemes[1].stylesheets,
+            ["css/bootstrap-slate.css", "css/solarized-dark.css"]
+        );
+        assert_eq!(
+            colors.stylesheet(),
+            "/* Generated from [colors] in site.toml. */\n\
+             :root {\n    color-scheme: light dark;\n    --background: #fff;\n}\n\
+             @media (prefers-color-scheme: dark) {\n    :root {\n        \
+             --background: #272b30;\n        --text: #aaa;\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn fixed_mode_always_uses_its_scheme() {
+        let colors = colors(&format!("mode = \"dark\"\n{}", BOTH));
+        let schemes = colors.schemes();
+        assert_eq!(schemes.len(), 1);
+        assert_eq!((schemes[0].name, schemes[0].media), ("dark", "all"));
+        assert!(colors.stylesheet().contains("color-scheme: dark;"));
+        assert!(!colors.stylesheet().contains("@media"));
+    }
+
+    #[test]
+    fn rejects_vars_which_break_the_stylesheet() {
+        assert!(colors(BOTH).validate().is_ok());
+        assert!(colors("[dark.vars]\n\"a b\" = \"red\"").validate().is_err());
+        assert!(
+            colors("[dark.vars]\na = \"red; } body { display: none\"")
+                .validate()
+                .is_err()
+        );
+        assert!(colors("highlight = \"../x\"").validate().is_err());
+    }
+}
@@ -0,0 +1,264 @@
+use crate::colors::ColorsConfig;
+use crate::error::SiteError;
+use miette::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// SITE_CONFIG is the default relative path to the site configuration.
+pub static SITE_CONFIG: &str = "site.toml";
+
+/// SiteConfig is the declarative part of the site, read from
+/// [SITE_CONFIG](crate::config::SITE_CONFIG).
+///
+/// # Example
+///
+/// theme = "slate"
+///
+/// [colors]
+/// highlight = "solarized"
+///
+/// [[pages]]
+/// uri = "/talks"
+/// template = "talks.html.tera"
+/// title = "talks"
+///
+/// [[menu]]
+/// label = "Talks"
+/// uri = "/talks"
+/// order = 70
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SiteConfig {
+    /// theme is the name of the theme in [THEMES_DIR](crate::theme::THEMES_DIR)
+    /// providing the default templates and static files, if any.
+    pub theme: Option<String>,
+    /// colors are the light and dark color schemes, see [ColorsConfig](crate::colors::ColorsConfig).
+    #[serde(default)]
+    pub colors: ColorsConfig,
+    /// pages are the standalone pages rendered from a single template.
+    #[serde(default)]
+    pub pages: Vec<PageConfig>,
+    /// menu is the navigation menu, sorted by [order](crate::config::MenuItem::order).
+    #[serde(default)]
+    pub menu: Vec<MenuItem>,
+    /// map is the tile server of the photo map, see [MapConfig](crate::config::MapConfig).
+    #[serde(default)]
+    pub map: MapConfig,
+}
+
+/// MapConfig is the `[map]` table of the [SiteConfig](crate::config::SiteConfig).
+/// Without `tiles` the photo map only shows the photos, on a plain background.
+///
+/// # Example
+///
+/// [map]
+/// tiles = "https://tile.openstreetmap.org/{z}/{x}/{y}.png"
+/// attribution = "&copy; <a href=\"https://www.openstreetmap.org/copyright\">OpenStreetMap</a> contributors"
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct MapConfig {
+    /// tiles is the URL template of the map tiles, with `{z}`, `{x}` and `{y}`.
+    pub tiles: Option<String>,
+    /// attribution is the HTML credit the tile server asks for, shown on the map.
+    pub attribution: Option<String>,
+}
+
+/// PageConfig describes a single page rendered from a template with the
+/// base context (see [get_base_context](crate::context::get_base_context)).
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PageConfig {
+    /// uri is where the page lives, which also decides the active menu entry.
+    pub uri: String,
+    /// template is the Tera template to render, relative to `templates/`.
+    pub template: String,
+    /// title is the page title, used by both the template and the page metadata.
+    pub title: Option<String>,
+    /// output is the file to write, relative to the output root.
+    /// Defaults to `index.html` under `uri`.
+    pub output: Option<String>,
+}
+
+/// MenuItem is a single link in the navigation menu.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MenuItem {
+    pub label: String,
+    pub uri: String,
+    /// order decides where the item goes; items with the same order keep
+    /// the order they are listed in.
+    #[serde(default)]
+    pub order: i32,
+    /// external links open in a new tab and are never active.
+    #[serde(default)]
+    pub external: bool,
+}
+
+impl MenuItem {
+    /// is_active returns whether the item should be highlighted on the page at `page_uri`:
+    /// either it links to that page, or to a section containing it (i.e. "/blog" for "/blog/post").
+    pub fn is_active(&self, page_uri: &str) -> bool {
+        if self.external {
+            return false;
+        }
+        let uri = self.uri.trim_end_matches('/');
+        let page_uri = page_uri.trim_end_matches('/');
+        uri == page_uri || (!uri.is_empty() && page_uri.starts_with(&format!("{}/", uri)))
+    }
+}
+
+impl PageConfig {
+    /// output_path returns the file the page is written to,
+    /// i.e. "/talks" is written to "talks/index.html".
+    pub fn output_path(&self) -> String {
+        if let Some(output) = &self.output {
+            return output.trim_start_matches('/').to_owned();
+        }
+        match self.uri.trim_matches('/') {
+            "" => "index.html".to_owned(),
+            dir => format!("{}/index.html", dir),
+        }
+    }
+}
+
+impl MapConfig {
+    /// validate checks the tile URL has every placeholder, and that the tiles
+    /// are credited: tile servers require their attribution on the map.
+    pub fn validate(&self) -> Result<()> {
+        let Some(tiles) = &self.tiles else {
+            return Ok(());
+        };
+        if ["{z}", "{x}", "{y}"].iter().any(|p| !tiles.contains(p)) {
+            return Err(SiteError::InvalidMap(format!("tiles = {:?}", tiles)).into());
+        }
+        if self
+            .attribution
+            .as_deref()
+            .is_none_or(|a| a.trim().is_empty())
+        {
+            return Err(SiteError::InvalidMap("tiles need an attribution".to_owned()).into());
+        }
+        Ok(())
+    }
+}
+
+impl SiteConfig {
+    /// parse reads a configuration from its toml source.
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut config: SiteConfig = toml::from_str(source).map_err(SiteError::from)?;
+        config.menu.sort_by_key(|item| item.order);
+        config.colors.validate()?;
+        config.map.validate()?;
+        let mut outputs = HashSet::new();
+        for page in &config.pages {
+            if !outputs.insert(page.output_path()) {
+                return Err(SiteError::DuplicatePage(page.output_path()).into());
+            }
+        }
+        Ok(config)
+    }
+
+    /// load reads the configuration at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|_| SiteError::FileNotFound(path.to_string_lossy().into_owned()))?;
+        SiteConfig::parse(&source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_default_to_index_under_uri() {
+        let config = SiteConfig::parse(
+            r#"
+            [[pages]]
+            uri = "/"
+            template = "index.html.tera"
+            title = "home"
+
+            [[pages]]
+            uri = "/talks/"
+            template = "talks.html.tera"
+
+            [[pages]]
+            uri = "/"
+            template = "404.html.tera"
+            output = "404.html"
+            "#,
+        )
+        .unwrap();
+        let outputs: Vec<String> = config.pages.iter().map(PageConfig::output_path).collect();
+        assert_eq!(outputs, ["index.html", "talks/index.html", "404.html"]);
+        assert_eq!(config.pages[0].title.as_deref(), Some("home"));
+        assert_eq!(config.pages[1].title, None);
+    }
+
+    #[test]
+    fn menu_is_ordered_and_tracks_the_active_section() {
+        let config = SiteConfig::parse(
+            r#"
+            [[menu]]
+            label = "Blog"
+            uri = "/blog"
+            order = 2
+
+            [[menu]]
+            label = "Home"
+            uri = "/"
+            order = 1
+
+            [[menu]]
+            label = "Source"
+            uri = "https://github.com/dpbriggs/dpbriggs-blog"
+            order = 3
+            external = true
+            "#,
+        )
+        .unwrap();
+        let labels: Vec<&str> = config.menu.iter().map(|item| item.label.as_str()).collect();
+        assert_eq!(labels, ["Home", "Blog", "Source"]);
+
+        let [home, blog, source] = &config.menu[..] else {
+            panic!("expected three menu items");
+        };
+        assert!(home.is_active("/"));
+        assert!(!home.is_active("/blog"));
+        assert!(blog.is_active("/blog"));
+        assert!(blog.is_active("/blog/some-post/"));
+        assert!(!blog.is_active("/blogroll"));
+        assert!(!source.is_active("https://github.com/dpbriggs/dpbriggs-blog"));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_duplicate_pages() {
+        assert!(
+            SiteConfig::parse("[[pages]]\nuri = \"/\"\ntemplate = \"a\"\ntitel = \"b\"").is_err()
+        );
+        let duplicate = "[[pages]]\nuri = \"/a\"\ntemplate = \"a\"\n\
+                         [[pages]]\nuri = \"/a/\"\ntemplate = \"b\"";
+        assert!(SiteConfig::parse(duplicate).is_err());
+    }
+
+    #[test]
+    fn map_tiles_need_placeholders_and_attribution() {
+        let config = SiteConfig::parse(
+            "[map]\ntiles = \"https://tiles.example/{z}/{x}/{y}.png\"\nattribution = \"Example\"",
+        )
+        .unwrap();
+        assert_eq!(config.map.attribution.as_deref(), Some("Example"));
+        assert!(SiteConfig::parse("").unwrap().map.tiles.is_none());
+
+        assert!(
+            SiteConfig::parse("[map]\ntiles = \"https://tiles.example/{z}/{x}/{y}.png\"").is_err()
+        );
+        let missing_y =
+            "[map]\ntiles = \"https://tiles.example/{z}/{x}.png\"\nattribution = \"Example\"";
+        assert!(SiteConfig::parse(missing_y).is_err());
+    }
+}
@@ -1,8 +1,17 @@
+use chrono::NaiveDate;
 use lazy_static::lazy_static;
+use select::document::Document;
+use select::predicate::Name;
 use serde::Serialize;
+use serde_json::json;
 use std::collections::HashMap;
 
 use crate::blog::{OrgBlog, OrgModeHtml};
+use crate::cards::CARD_FILENAME;
+use crate::colors::SchemeEntry;
+use crate::config::{MapConfig, SiteConfig};
+use crate::pages::ContentPage;
+use crate::pics::{Album, Photo, PicSession, PicsIndex, TAGS_URI};
 
 /// BLOG_ROOT is the relative path to blog
 pub static BLOG_ROOT: &str = "blog/";
@@ -10,29 +19,94 @@ pub static BLOG_ROOT: &str = "blog/";
 /// PICS_ROOT is the relative path to pics
 pub static PICS_ROOT: &str = "pics/";
 
-/// SiteContextKv represents all key-value variables used in
-/// this project.
-///
-/// # Example
-///
-/// let mut foo = SiteContextKv::new()
-/// foo.insert("key".to_owned(), "value".to_owned())
+/// PAGES_ROOT is the relative path to standalone content pages
+pub static PAGES_ROOT: &str = "pages/";
+
+/// SiteContextKv represents the static key-value variables of this project,
+/// see [STATIC_SITE_CONTEXT_KV](crate::context::STATIC_SITE_CONTEXT_KV).
 type SiteContextKv = HashMap<String, String>;
 
 /// SiteContext represents the entire context required to render
-/// this website. See [get_base_context](crate::context::get_base_context)
+/// a page of this website. See [get_base_context](crate::context::get_base_context)
 #[derive(Serialize, Debug)]
 pub struct SiteContext<'a> {
     /// base is the static key-value context of the website.
     /// All of the information in base comes from
     /// [STATIC_SITE_CONTEXT_KV](crate::context::STATIC_SITE_CONTEXT_KV)
     pub base: &'static SiteContextKv,
-    /// kv is the dynamic key-value context of the website.
-    pub kv: SiteContextKv,
+    /// page is the page being rendered, see [PageContext](crate::context::PageContext)
+    pub page: PageContext,
+    /// menu is the navigation menu, with the entry for the current page marked active.
+    pub menu: Vec<MenuEntry<'a>>,
+    /// colors are the color schemes the page links, see [SchemeEntry](crate::colors::SchemeEntry)
+    pub colors: Vec<SchemeEntry>,
+    /// map is the tile server of the photo map, see [MapConfig](crate::config::MapConfig)
+    pub map: &'a MapConfig,
+    /// meta is the per-page SEO metadata, see [PageMeta](crate::context::PageMeta)
+    pub meta: PageMeta,
     /// blog is all blog related items, see [OrgBlog](crate::context::OrgBlog)
     pub blog: &'a OrgBlog,
-    /// curr_blog is the current blog article, if applicable.
-    pub curr_blog: Option<&'a OrgModeHtml>,
+    /// article is the current blog article, if applicable.
+    pub article: Option<&'a OrgModeHtml>,
+    /// content is the current content page, if applicable.
+    pub content: Option<&'a ContentPage>,
+    /// pics_index is the current page of the pics index or a year's archive.
+    pub pics_index: Option<&'a PicsIndex<'a>>,
+    /// session is the current photo session, if applicable.
+    pub session: Option<&'a PicSession>,
+    /// photo is the current photo of the session, on its own page.
+    pub photo: Option<&'a Photo>,
+    /// photo_nav links the photos before and after the current photo.
+    pub photo_nav: Option<PhotoNav<'a>>,
+    /// albums are every tag's album, on the tags index.
+    pub albums: Option<&'a [Album<'a>]>,
+    /// album is the current tag's album, if applicable.
+    pub album: Option<&'a Album<'a>>,
+}
+
+/// PhotoNav is the previous and next photo in a session, from a photo's page.
+#[derive(Serialize, Debug)]
+pub struct PhotoNav<'a> {
+    pub prev: Option<&'a Photo>,
+    pub next: Option<&'a Photo>,
+}
+
+/// PageContext is what every template knows about the page being rendered.
+#[derive(Serialize, Debug)]
+pub struct PageContext {
+    pub title: String,
+    /// path is where the page lives, i.e. "/blog".
+    pub path: String,
+    /// nav is the uri of the active menu entry, if any.
+    pub nav: Option<String>,
+}
+
+/// MenuEntry is a [MenuItem](crate::config::MenuItem) as shown on a particular page.
+#[derive(Serialize, Debug)]
+pub struct MenuEntry<'a> {
+    pub label: &'a str,
+    pub uri: &'a str,
+    pub external: bool,
+    pub active: bool,
+}
+
+/// PageMeta holds the per-page metadata used for link previews
+/// (Open Graph, Twitter cards) and structured data (JSON-LD).
+#[derive(Serialize, Debug, Default)]
+pub struct PageMeta {
+    pub title: String,
+    /// canonical_url is where the page is served from, always ending in a slash like
+    /// the directories pages are written to. Pages not served from their uri have none.
+    pub canonical_url: Option<String>,
+    pub description: String,
+    /// og_type is the Open Graph type, either "website" or "article".
+    pub og_type: &'static str,
+    /// image is an absolute URL to the preview image, if any.
+    pub image: Option<String>,
+    pub published: Option<NaiveDate>,
+    pub author: String,
+    /// json_ld is the serialized schema.org object for this page, if any.
+    pub json_ld: Option<String>,
 }
 
 macro_rules! site_context(
@@ -51,13 +125,10 @@ lazy_static! {
     static ref STATIC_SITE_CONTEXT_KV: SiteContextKv = {
         site_context! {
             "domain_name" =>  "dpbriggs.ca",
-            "nav_site_href" =>  "/",
+            "site_description" => "David Briggs' personal website: blog posts, photos, and resume.",
             "root_uri" =>  "/",
             "blog_uri" =>  "/blog",
             "resume_uri" =>  "/resume",
-            "linkedin_uri" =>  "/linkedin",
-            "github_uri" =>  "/github",
-            "resume_uri" =>  "/resume",
             "resume_pdf_uri" =>  "/dpbriggs_resume.pdf",
             "rss_uri" =>  "/feed/index.xml",
             "crash_uri" =>  "/500",
@@ -74,30 +145,244 @@ lazy_static! {
     };
 }
 
-use tera::Context;
+/// absolute_url turns a site path (or an already absolute URL) into an absolute URL.
+pub fn absolute_url(path: &str) -> String {
+    if path.starts_with("http://") || path.starts_with("https://") {
+        return path.to_owned();
+    }
+    let path = path.trim_start_matches("./");
+    let sep = if path.starts_with('/') { "" } else { "/" };
+    format!(
+        "https://{}{}{}",
+        STATIC_SITE_CONTEXT_KV["domain_name"], sep, path
+    )
+}
+
+/// strip_html returns the text content of an html fragment.
+pub(crate) fn strip_html(html: &str) -> String {
+    Document::from(html)
+        .find(Name("body"))
+        .next()
+        .map(|body| body.text())
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
-impl<'a> From<&SiteContext<'a>> for Context {
-    fn from(site_context: &SiteContext<'a>) -> Self {
-        let mut context = Context::new();
-        context.insert("base", &site_context.base);
-        context.insert("kv", &site_context.kv);
-        context.insert("blog", &site_context.blog);
-        context.insert("curr_blog", &site_context.curr_blog);
-        context
+/// json_ld serializes a schema.org object so it can be inlined in a script tag.
+fn json_ld(value: serde_json::Value) -> String {
+    value.to_string().replace("</", "<\\/")
+}
+
+impl PageMeta {
+    /// new creates the default metadata for a plain page at `path`.
+    pub fn new(path: &str, title: &str) -> Self {
+        let path = match path.ends_with('/') {
+            true => path.to_owned(),
+            false => format!("{}/", path),
+        };
+        PageMeta {
+            title: title.to_owned(),
+            canonical_url: Some(absolute_url(&path)),
+            description: STATIC_SITE_CONTEXT_KV["site_description"].clone(),
+            og_type: "website",
+            image: None,
+            published: None,
+            author: STATIC_SITE_CONTEXT_KV["full_name"].clone(),
+            json_ld: None,
+        }
+    }
+
+    /// article creates the metadata for a blog article, including
+    /// a schema.org `BlogPosting`.
+    pub fn article(post: &OrgModeHtml) -> Self {
+        let path = format!("{}/{}", STATIC_SITE_CONTEXT_KV["blog_uri"], post.slug);
+        let mut meta = PageMeta::new(&path, &post.title);
+        meta.og_type = "article";
+        meta.description = post.desc.split_whitespace().collect::<Vec<_>>().join(" ");
+        meta.published = Some(post.date);
+        // Prefer the article's own cover, falling back to the generated card.
+        meta.image = Some(match post.cover.as_deref() {
+            Some(cover) if cover.starts_with('/') || cover.contains("://") => absolute_url(cover),
+            Some(cover) => absolute_url(&format!("{}/{}", path, cover)),
+            None => absolute_url(&format!("{}/{}", path, CARD_FILENAME)),
+        });
+        meta.json_ld = Some(json_ld(json!({
+            "@context": "https://schema.org",
+            "@type": "BlogPosting",
+            "headline": meta.title,
+            "description": meta.description,
+            "datePublished": post.date.to_string(),
+            "url": meta.canonical_url,
+            "mainEntityOfPage": meta.canonical_url,
+            "image": meta.image,
+            "author": { "@type": "Person", "name": meta.author },
+        })));
+        meta
+    }
+
+    /// content_page creates the metadata for a standalone content page,
+    /// including a schema.org `WebPage`.
+    pub fn content_page(page: &ContentPage) -> Self {
+        let mut meta = PageMeta::new(&format!("/{}", page.slug), &page.title);
+        if !page.desc.is_empty() {
+            meta.description = page.desc.split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+        meta.json_ld = Some(json_ld(json!({
+            "@context": "https://schema.org",
+            "@type": "WebPage",
+            "name": meta.title,
+            "description": meta.description,
+            "url": meta.canonical_url,
+            "author": { "@type": "Person", "name": meta.author },
+        })));
+        meta
+    }
+
+    /// pic_session creates the metadata for a photo session, including
+    /// a schema.org `ImageGallery`.
+    pub fn pic_session(session: &PicSession) -> Self {
+        let path = format!("{}/{}/", STATIC_SITE_CONTEXT_KV["pics_uri"], session.slug);
+        let mut meta = PageMeta::new(&path, &session.title);
+        let description = strip_html(&session.description);
+        if !description.is_empty() {
+            meta.description = description;
+        }
+        meta.published = Some(session.date);
+        let images: Vec<String> = session
+            .shown_photos()
+            .map(|photo| absolute_url(&format!("{}{}", path, photo.filename)))
+            .collect();
+        meta.image = Some(absolute_url(&format!("{}{}", path, CARD_FILENAME)));
+        meta.json_ld = Some(json_ld(json!({
+            "@context": "https://schema.org",
+            "@type": "ImageGallery",
+            "name": meta.title,
+            "description": meta.description,
+            "datePublished": session.date.to_string(),
+            "url": meta.canonical_url,
+            "image": images,
+            "author": { "@type": "Person", "name": meta.author },
+        })));
+        meta
+    }
+
+    /// album creates the metadata for a tag's album, including a schema.org
+    /// `ImageGallery`. The first photo is the preview image.
+    pub fn album(album: &Album) -> Self {
+        let path = format!("{}/{}/", TAGS_URI, album.slug);
+        let mut meta = PageMeta::new(&path, &format!("pics tagged {}", album.tag));
+        meta.description = format!("Photos tagged {} across every session.", album.tag);
+        let images: Vec<String> = album
+            .photos
+            .iter()
+            .map(|item| {
+                let photo_path = format!(
+                    "{}/{}/",
+                    STATIC_SITE_CONTEXT_KV["pics_uri"], item.session_slug
+                );
+                absolute_url(&format!("{}{}", photo_path, item.photo.filename))
+            })
+            .collect();
+        meta.image = images.first().cloned();
+        meta.json_ld = Some(json_ld(json!({
+            "@context": "https://schema.org",
+            "@type": "ImageGallery",
+            "name": meta.title,
+            "description": meta.description,
+            "url": meta.canonical_url,
+            "image": images,
+            "author": { "@type": "Person", "name": meta.author },
+        })));
+        meta
+    }
+
+    /// pic_photo creates the metadata for a single photo's page, including
+    /// a schema.org `Photograph`. The photo itself is the preview image.
+    pub fn pic_photo(session: &PicSession, photo: &Photo) -> Self {
+        let session_path = format!("{}/{}/", STATIC_SITE_CONTEXT_KV["pics_uri"], session.slug);
+        let path = format!("{}{}/", session_path, photo.slug);
+        let title = photo
+            .title
+            .as_deref()
+            .map(strip_html)
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| session.title.clone());
+        let mut meta = PageMeta::new(&path, &title);
+        let description = photo
+            .description
+            .as_deref()
+            .map(strip_html)
+            .unwrap_or_default();
+        meta.description = if description.is_empty() {
+            photo.alt.clone()
+        } else {
+            description
+        };
+        meta.published = Some(photo.date.unwrap_or(session.date));
+        meta.image = Some(absolute_url(&format!("{}{}", session_path, photo.filename)));
+        meta.json_ld = Some(json_ld(json!({
+            "@context": "https://schema.org",
+            "@type": "Photograph",
+            "name": meta.title,
+            "description": meta.description,
+            "dateCreated": meta.published.map(|date| date.to_string()),
+            "url": meta.canonical_url,
+            "image": meta.image,
+            "isPartOf": absolute_url(&session_path),
+            "author": { "@type": "Person", "name": meta.author },
+        })));
+        meta
     }
 }
 
-/// get_base_context
-pub fn get_base_context<'a>(nav_href_uri: &str, blog: &'a OrgBlog) -> SiteContext<'a> {
+/// get_base_context returns the context shared by every page, for the page at `page_uri`.
+pub fn get_base_context<'a>(
+    page_uri: &str,
+    blog: &'a OrgBlog,
+    config: &'a SiteConfig,
+) -> SiteContext<'a> {
+    let menu: Vec<MenuEntry> = config
+        .menu
+        .iter()
+        .map(|item| MenuEntry {
+            label: &item.label,
+            uri: &item.uri,
+            external: item.external,
+            active: item.is_active(page_uri),
+        })
+        .collect();
     SiteContext {
         base: &STATIC_SITE_CONTEXT_KV,
-        // TODO: Not waste memory like this.
-        kv: {
-            let mut tmp = SiteContextKv::new();
-            tmp.insert("nav_site_href".to_owned(), nav_href_uri.to_owned());
-            tmp
+        page: PageContext {
+            title: String::new(),
+            path: page_uri.to_owned(),
+            nav: menu
+                .iter()
+                .find(|entry| entry.active)
+                .map(|entry| entry.uri.to_owned()),
         },
+        menu,
+        colors: config.colors.schemes(),
+        map: &config.map,
+        meta: PageMeta::new(page_uri, ""),
         blog,
-        curr_blog: None,
+        article: None,
+        content: None,
+        pics_index: None,
+        session: None,
+        photo: None,
+        photo_nav: None,
+        albums: None,
+        album: None,
+    }
+}
+
+impl SiteContext<'_> {
+    /// set_title sets the page title used by both the template and the page metadata.
+    pub fn set_title(&mut self, title: &str) {
+        self.page.title = title.to_owned();
+        self.meta.title = title.to_owned();
     }
 }
@@ -14,8 +14,32 @@ pub enum SiteError {
     TeraError(#[from] tera::Error),
 
     #[error(transparent)]
-    #[diagnostic(code(app::fs_extra_error))]
-    FsExtraError(#[from] fs_extra::error::Error),
+    #[diagnostic(code(app::zip_error))]
+    ZipError(#[from] zip::result::ZipError),
+
+    #[error(transparent)]
+    #[diagnostic(code(app::config_error))]
+    ConfigError(#[from] toml::de::Error),
+
+    #[error("More than one page is written to {0}")]
+    #[diagnostic(code(app::duplicate_page))]
+    DuplicatePage(String),
+
+    #[error("More than one file is written to {0}")]
+    #[diagnostic(code(app::duplicate_file))]
+    DuplicateFile(PathBuf),
+
+    #[error("Invalid color scheme setting: {0}")]
+    #[diagnostic(code(app::invalid_color))]
+    InvalidColor(String),
+
+    #[error("Invalid map setting: {0}")]
+    #[diagnostic(code(app::invalid_map))]
+    InvalidMap(String),
+
+    #[error("Template {0} uses variables missing from its context: {1}")]
+    #[diagnostic(code(app::undefined_variables))]
+    UndefinedVariables(String, String),
 
     #[error("File not found: {0}")]
     #[diagnostic(code(app::file_not_found))]
@@ -25,7 +49,23 @@ pub enum SiteError {
     #[diagnostic(code(app::parsing_error))]
     ParsingError(#[from] ParsingError),
 
+    #[error(transparent)]
+    #[diagnostic(code(app::image_error))]
+    ImageError(#[from] image::ImageError),
+
+    #[error(transparent)]
+    #[diagnostic(code(app::invalid_font))]
+    InvalidFont(#[from] ab_glyph::InvalidFont),
+
+    #[error("{0} is named like a generated file, rename it")]
+    #[diagnostic(code(app::reserved_file_name))]
+    ReservedFileName(PathBuf),
+
     #[error("Not a directory: {0}")]
     #[diagnostic(code(app::not_a_directory))]
     NotADirectory(PathBuf),
+
+    #[error("Unknown archive format (expected .tar.gz, .tgz or .zip): {0}")]
+    #[diagnostic(code(app::unknown_archive_format))]
+    UnknownArchiveFormat(PathBuf),
 }
\ No newline at end of file
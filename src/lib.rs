@@ -0,0 +1,34 @@
+//! dpbriggs-blog generates the static site for dpbriggs.ca.
+//!
+//! The [SiteBuilder](crate::builder::SiteBuilder) ties everything together:
+//!
+//! let mut sink = DirSink::new("public");
+//! SiteBuilder::new(&mut sink).blog_root("blog/").build()?;
+
+extern crate log;
+
+mod assets;
+pub mod blog;
+pub mod builder;
+pub mod camera;
+mod cards;
+pub mod colors;
+pub mod config;
+pub mod context;
+pub mod error;
+mod minify;
+pub mod output;
+pub mod pages;
+pub mod pics;
+pub mod placeholder;
+mod precompress;
+mod routes;
+mod template_vars;
+pub mod theme;
+#[cfg(test)]
+mod tests;
+
+pub use crate::blog::OrgBlog;
+pub use crate::builder::SiteBuilder;
+pub use crate::context::SiteContext;
+pub use crate::pics::PicsGallery;
@@ -1,23 +1,12 @@
-extern crate log;
-
-mod blog;
-mod context;
-mod error;
-mod pics;
-mod routes;
-
 use clap::Parser;
-use fs_extra::dir::{self, CopyOptions};
+use dpbriggs_blog::SiteBuilder;
+use dpbriggs_blog::builder::STATIC_DIR;
+use dpbriggs_blog::config::SITE_CONFIG;
+use dpbriggs_blog::context::{BLOG_ROOT, PAGES_ROOT, PICS_ROOT};
+use dpbriggs_blog::output::{DirSink, OutputSink, sink_for_archive};
+use dpbriggs_blog::theme::{TEMPLATES_DIR, THEMES_DIR};
 use miette::Result;
-use std::fs;
 use std::path::Path;
-use tera::Tera;
-
-use crate::blog::get_org_blog;
-use crate::context::{BLOG_ROOT, PICS_ROOT};
-use crate::error::SiteError;
-use crate::pics::get_pics_gallery;
-use crate::routes::generate_site;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -28,51 +17,63 @@ struct Cli {
     /// Output directory.
     #[arg(long, default_value = "public")]
     output_dir: String,
+    /// Write the site into a .tar.gz or .zip archive instead of the output directory.
+    #[arg(long)]
+    archive: Option<String>,
+    /// Site configuration listing the pages to render.
+    #[arg(long, default_value = SITE_CONFIG)]
+    config: String,
+    /// Directory of dated org-mode blog articles.
+    #[arg(long, default_value = BLOG_ROOT)]
+    blog_root: String,
+    /// Directory of standalone org-mode and Markdown pages.
+    #[arg(long, default_value = PAGES_ROOT)]
+    pages_root: String,
+    /// Directory of dated pics sessions.
+    #[arg(long, default_value = PICS_ROOT)]
+    pics_root: String,
+    /// Directory of static files served under /static, overriding the theme's.
+    #[arg(long, default_value = STATIC_DIR)]
+    static_dir: String,
+    /// Directory of the site's own templates, overriding the theme's.
+    #[arg(long, default_value = TEMPLATES_DIR)]
+    templates_dir: String,
+    /// Directory of themes, see `theme` in the site configuration.
+    #[arg(long, default_value = THEMES_DIR)]
+    themes_dir: String,
+    /// Minify generated html pages and static css/js.
+    #[arg(long)]
+    minify: bool,
+    /// Write .gz and .br siblings for compressible output files.
+    #[arg(long)]
+    precompress: bool,
+    /// Cache of compressed files, reused across builds.
+    #[arg(long, default_value = ".precompress-cache")]
+    precompress_cache: String,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    let tera = Tera::new("templates/**/*.tera").map_err(SiteError::from)?;
+    let mut sink: Box<dyn OutputSink> = match &cli.archive {
+        Some(archive) => sink_for_archive(Path::new(archive))?,
+        None => Box::new(DirSink::new(&cli.output_dir)),
+    };
 
-    // Create the output directory
-    let output_dir = &cli.output_dir;
-    println!("Creating output directory: {}", output_dir);
-    if fs::metadata(output_dir).is_ok() {
-        fs::remove_dir_all(output_dir).map_err(SiteError::from)?;
+    let mut builder = SiteBuilder::new(sink.as_mut())
+        .config(&cli.config)
+        .blog_root(&cli.blog_root)
+        .pages_root(&cli.pages_root)
+        .pics_root(&cli.pics_root)
+        .static_dir(&cli.static_dir)
+        .templates_dir(&cli.templates_dir)
+        .themes_dir(&cli.themes_dir)
+        .minify(cli.minify);
+    for path in &cli.extra_paths {
+        builder = builder.extra_path(path);
     }
-    fs::create_dir(output_dir).map_err(SiteError::from)?;
-
-    // Copy static files
-    let static_dir = "static";
-    println!("Copying static files from: {}", static_dir);
-    let output_static_dir = format!("{}/{}", output_dir, static_dir);
-    let mut copy_options = CopyOptions::new();
-    copy_options.overwrite = true;
-    copy_options.content_only = true;
-    dir::copy(static_dir, &output_static_dir, &copy_options).map_err(SiteError::from)?;
-
-    // Copy extra paths
-    for path_str in &cli.extra_paths {
-        let path = Path::new(path_str);
-        println!("Copying extra path: {:?}", path);
-        let file_name = path
-            .file_name()
-            .ok_or_else(|| SiteError::FileNotFound(path_str.clone()))?;
-        let dest_path = Path::new(output_dir).join(file_name);
-        if path.is_dir() {
-            dir::copy(path, dest_path, &copy_options).map_err(SiteError::from)?;
-        } else {
-            fs::copy(path, dest_path).map_err(SiteError::from)?;
-        }
+    if cli.precompress {
+        builder = builder.precompress(&cli.precompress_cache);
     }
-
-    let blog = get_org_blog(BLOG_ROOT)?;
-    let pics = get_pics_gallery(PICS_ROOT)?;
-
-    println!("Generating site...");
-    generate_site(&tera, output_dir, &blog, &pics)?;
-    println!("Site generation complete.");
-
-    Ok(())
+    builder.build()
 }
@@ -0,0 +1,98 @@
+use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
+use minify_js::{Session, TopLevelMode};
+
+fn html_cfg() -> minify_html::Cfg {
+    minify_html::Cfg {
+        keep_closing_tags: true,
+        keep_html_and_head_opening_tags: true,
+        minify_css: true,
+        minify_js: true,
+        ..minify_html::Cfg::spec_compliant()
+    }
+}
+
+/// minify_html minifies a rendered page, including inline `<style>` and
+/// `<script>` blocks. Whitespace inside `<pre>` is preserved.
+pub fn minify_html(html: &str) -> Vec<u8> {
+    minify_html::minify(html.as_bytes(), &html_cfg())
+}
+
+/// minify_css minifies a stylesheet, returning None if it can't be parsed.
+pub fn minify_css(css: &str) -> Option<String> {
+    let mut stylesheet = StyleSheet::parse(css, ParserOptions::default()).ok()?;
+    stylesheet.minify(MinifyOptions::default()).ok()?;
+    let printer = PrinterOptions {
+        minify: true,
+        ..PrinterOptions::default()
+    };
+    Some(stylesheet.to_css(printer).ok()?.code)
+}
+
+/// minify_js minifies a script, returning None if it can't be parsed.
+pub fn minify_js(js: &[u8]) -> Option<Vec<u8>> {
+    let session = Session::new();
+    let mut out = Vec::new();
+    minify_js::minify(&session, TopLevelMode::Global, js, &mut out).ok()?;
+    Some(out)
+}
+
+/// minify_asset minifies a css or js file from the static directory.
+/// Other files, files which are already minified (`*.min.*`) and files
+/// which fail to minify are returned unchanged.
+pub fn minify_asset(rel_path: &str, contents: Vec<u8>) -> Vec<u8> {
+    if rel_path.contains(".min.") {
+        return contents;
+    }
+    let minified = if rel_path.ends_with(".css") {
+        std::str::from_utf8(&contents)
+            .ok()
+            .and_then(minify_css)
+            .map(String::into_bytes)
+    } else if rel_path.ends_with(".js") {
+        minify_js(&contents)
+    } else {
+        return contents;
+    };
+    match minified {
+        Some(minified) if minified.len() < contents.len() => minified,
+        Some(_) => contents,
+        None => {
+            eprintln!("Warning: failed to minify {}, copying as-is", rel_path);
+            contents
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_preserves_pre_blocks() {
+        let html = "<html><body>\n  <p>  some   text </p>\n<pre>fn main() {\n    println!(\"hi\");\n}</pre>\n</body></html>";
+        let minified = String::from_utf8(minify_html(html)).unwrap();
+        assert!(minified.contains("<pre>fn main() {\n    println!(\"hi\");\n}</pre>"));
+        assert!(minified.contains("<p>some text</p>"));
+    }
+
+    #[test]
+    fn css_and_js_are_minified() {
+        assert_eq!(
+            minify_asset("css/site.css", b"a {\n  color: #ff0000;\n}\n".to_vec()),
+            b"a{color:red}"
+        );
+        let js = minify_asset(
+            "js/site.js",
+            b"function add(first, second) {\n  return first + second;\n}\n".to_vec(),
+        );
+        assert!(js.len() < 40, "{}", String::from_utf8_lossy(&js));
+    }
+
+    #[test]
+    fn skips_minified_and_broken_assets() {
+        let min = b"a {  color: red; }".to_vec();
+        assert_eq!(minify_asset("css/bootstrap.min.css", min.clone()), min);
+        let broken = b"function (".to_vec();
+        assert_eq!(minify_asset("js/broken.js", broken.clone()), broken);
+    }
+}
@@ -0,0 +1,518 @@
+use crate::error::SiteError;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use miette::Result;
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+/// OutputSink is where a build writes the generated site.
+///
+/// Paths are relative to the root of the site, i.e. "blog/index.html".
+pub trait OutputSink {
+    /// begin is called once before anything is written.
+    fn begin(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// write stores `contents` at `path`, replacing anything already there.
+    fn write(&mut self, path: &Path, contents: &[u8]) -> Result<()>;
+
+    /// finish is called once the whole site has been written.
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// DirSink writes the site into a directory on the local filesystem.
+///
+/// The site is built in a staging directory next to `root`, which is only
+/// swapped into place by [finish](OutputSink::finish). If the build or the
+/// swap fails, the previous build in `root` is left untouched.
+pub struct DirSink {
+    root: PathBuf,
+    staging: PathBuf,
+}
+
+impl DirSink {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let staging = sibling(&root, "staging");
+        DirSink { root, staging }
+    }
+}
+
+/// sibling returns a hidden path next to `root`, i.e. public -> .public.staging
+fn sibling(root: &Path, suffix: &str) -> PathBuf {
+    let name = root
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "output".to_string());
+    root.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+/// remove_dir removes `dir` and everything in it, if it exists.
+fn remove_dir(dir: &Path) -> Result<()> {
+    if fs::metadata(dir).is_ok() {
+        fs::remove_dir_all(dir).map_err(SiteError::from)?;
+    }
+    Ok(())
+}
+
+/// exchange atomically swaps the directories `a` and `b`, so there's no moment
+/// where either is missing. Both must exist.
+#[cfg(target_os = "linux")]
+fn exchange(a: &Path, b: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    let a = CString::new(a.as_os_str().as_bytes())?;
+    let b = CString::new(b.as_os_str().as_bytes())?;
+    // SAFETY: both paths are NUL-terminated and outlive the call.
+    let ret = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            a.as_ptr(),
+            libc::AT_FDCWD,
+            b.as_ptr(),
+            libc::RENAME_EXCHANGE,
+        )
+    };
+    match ret {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn exchange(_: &Path, _: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// replace_dir moves `staging` to `root`, moving the previous `root` to `old` first.
+/// If `staging` can't be moved, the previous `root` is put back.
+fn replace_dir(staging: &Path, root: &Path, old: &Path) -> Result<()> {
+    let had_root = fs::metadata(root).is_ok();
+    if had_root {
+        fs::rename(root, old).map_err(SiteError::from)?;
+    }
+    if let Err(err) = fs::rename(staging, root) {
+        if had_root {
+            fs::rename(old, root).map_err(SiteError::from)?;
+        }
+        return Err(SiteError::from(err).into());
+    }
+    Ok(())
+}
+
+impl OutputSink for DirSink {
+    /// begin creates an empty staging directory, removing any left over
+    /// from a failed build.
+    fn begin(&mut self) -> Result<()> {
+        println!("Creating staging directory: {:?}", self.staging);
+        remove_dir(&self.staging)?;
+        fs::create_dir_all(&self.staging).map_err(SiteError::from)?;
+        Ok(())
+    }
+
+    fn write(&mut self, path: &Path, contents: &[u8]) -> Result<()> {
+        let path = self.staging.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(SiteError::from)?;
+        }
+        fs::write(path, contents).map_err(SiteError::from)?;
+        Ok(())
+    }
+
+    /// finish swaps the staging directory into place and removes the old build.
+    /// Where the filesystem can't exchange directories atomically, the old build
+    /// is moved aside first, and moved back if the new one can't take its place.
+    fn finish(&mut self) -> Result<()> {
+        println!("Swapping {:?} into {:?}", self.staging, self.root);
+        if fs::metadata(&self.root).is_ok() && exchange(&self.staging, &self.root).is_ok() {
+            // The staging directory now holds the old build.
+            return remove_dir(&self.staging);
+        }
+        let old = sibling(&self.root, "old");
+        remove_dir(&old)?;
+        replace_dir(&self.staging, &self.root, &old)?;
+        remove_dir(&old)
+    }
+}
+
+/// MemorySink keeps the site in memory, which is handy for tests.
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    pub files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// get returns the contents of the file at `path`, if it was written.
+    pub fn get(&self, path: impl AsRef<Path>) -> Option<&[u8]> {
+        self.files.get(path.as_ref()).map(Vec::as_slice)
+    }
+}
+
+impl OutputSink for MemorySink {
+    fn begin(&mut self) -> Result<()> {
+        self.files.clear();
+        Ok(())
+    }
+
+    fn write(&mut self, path: &Path, contents: &[u8]) -> Result<()> {
+        self.files.insert(path.to_path_buf(), contents.to_vec());
+        Ok(())
+    }
+}
+
+/// archive_path returns the name of `path` inside an archive, always using '/'.
+fn archive_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// source_date_epoch returns the `SOURCE_DATE_EPOCH` timestamp archive entries get,
+/// or the epoch itself, so building the same site twice gives the same archive.
+fn source_date_epoch() -> u64 {
+    env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// ArchiveFile is the file an archive sink writes to. The archive is written
+/// next to its final path and only renamed into place once it's complete,
+/// so a failed build never leaves a truncated archive behind.
+struct ArchiveFile {
+    path: PathBuf,
+    partial: PathBuf,
+    /// written is every path in the archive so far, archives can't replace entries.
+    written: HashSet<PathBuf>,
+}
+
+impl ArchiveFile {
+    fn new(path: PathBuf) -> Self {
+        let partial = sibling(&path, "partial");
+        ArchiveFile {
+            path,
+            partial,
+            written: HashSet::new(),
+        }
+    }
+
+    /// create starts a new, empty archive.
+    fn create(&mut self) -> Result<File> {
+        println!("Creating archive: {:?}", self.path);
+        self.written.clear();
+        Ok(File::create(&self.partial).map_err(SiteError::from)?)
+    }
+
+    /// add records `path` as written, failing if it already was.
+    fn add(&mut self, path: &Path) -> Result<String> {
+        if !self.written.insert(path.to_path_buf()) {
+            return Err(SiteError::DuplicateFile(path.to_path_buf()).into());
+        }
+        Ok(archive_path(path))
+    }
+
+    /// persist moves the complete archive into place.
+    fn persist(&self) -> Result<()> {
+        fs::rename(&self.partial, &self.path).map_err(SiteError::from)?;
+        Ok(())
+    }
+}
+
+impl Drop for ArchiveFile {
+    /// drop removes the archive of a build which didn't finish.
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.partial);
+    }
+}
+
+/// TarGzSink writes the site into a gzipped tarball, ready to deploy.
+pub struct TarGzSink {
+    file: ArchiveFile,
+    mtime: u64,
+    builder: Option<tar::Builder<GzEncoder<File>>>,
+}
+
+impl TarGzSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        TarGzSink {
+            file: ArchiveFile::new(path.into()),
+            mtime: source_date_epoch(),
+            builder: None,
+        }
+    }
+}
+
+impl OutputSink for TarGzSink {
+    fn begin(&mut self) -> Result<()> {
+        let encoder = GzEncoder::new(self.file.create()?, Compression::best());
+        self.builder = Some(tar::Builder::new(encoder));
+        Ok(())
+    }
+
+    fn write(&mut self, path: &Path, contents: &[u8]) -> Result<()> {
+        let name = self.file.add(path)?;
+        let builder = self
+            .builder
+            .as_mut()
+            .expect("TarGzSink::begin was not called");
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(self.mtime);
+        builder
+            .append_data(&mut header, name, contents)
+            .map_err(SiteError::from)?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some(builder) = self.builder.take() {
+            builder
+                .into_inner()
+                .and_then(GzEncoder::finish)
+                .map_err(SiteError::from)?;
+            self.file.persist()?;
+        }
+        Ok(())
+    }
+}
+
+/// ZipSink writes the site into a zip archive, ready to deploy.
+pub struct ZipSink {
+    file: ArchiveFile,
+    writer: Option<ZipWriter<File>>,
+}
+
+impl ZipSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        ZipSink {
+            file: ArchiveFile::new(path.into()),
+            writer: None,
+        }
+    }
+}
+
+impl OutputSink for ZipSink {
+    fn begin(&mut self) -> Result<()> {
+        self.writer = Some(ZipWriter::new(self.file.create()?));
+        Ok(())
+    }
+
+    fn write(&mut self, path: &Path, contents: &[u8]) -> Result<()> {
+        use std::io::Write;
+        let name = self.file.add(path)?;
+        let writer = self.writer.as_mut().expect("ZipSink::begin was not called");
+        writer
+            .start_file(name, SimpleFileOptions::default())
+            .map_err(SiteError::from)?;
+        writer.write_all(contents).map_err(SiteError::from)?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.take() {
+            writer.finish().map_err(SiteError::from)?;
+            self.file.persist()?;
+        }
+        Ok(())
+    }
+}
+
+/// sink_for_archive picks the archive sink matching the extension of `path`:
+/// `.tar.gz`/`.tgz` or `.zip`.
+pub fn sink_for_archive(path: &Path) -> Result<Box<dyn OutputSink>> {
+    let name = path.to_string_lossy();
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Ok(Box::new(TarGzSink::new(path)))
+    } else if name.ends_with(".zip") {
+        Ok(Box::new(ZipSink::new(path)))
+    } else {
+        Err(SiteError::UnknownArchiveFormat(path.to_path_buf()).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    fn write_site(sink: &mut dyn OutputSink) {
+        sink.begin().unwrap();
+        sink.write(Path::new("index.html"), b"<p>home</p>").unwrap();
+        sink.write(Path::new("blog/post/index.html"), b"<p>post</p>")
+            .unwrap();
+        sink.finish().unwrap();
+    }
+
+    #[test]
+    fn memory_sink_keeps_files() {
+        let mut sink = MemorySink::new();
+        write_site(&mut sink);
+        assert_eq!(sink.files.len(), 2);
+        assert_eq!(sink.get("blog/post/index.html"), Some(&b"<p>post</p>"[..]));
+    }
+
+    #[test]
+    fn dir_sink_keeps_previous_build_until_finished() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let root = tmp.path().join("public");
+        let mut sink = DirSink::new(&root);
+        write_site(&mut sink);
+        assert_eq!(fs::read(root.join("index.html")).unwrap(), b"<p>home</p>");
+
+        // A build which fails before finishing leaves the previous build alone.
+        let mut sink = DirSink::new(&root);
+        sink.begin().unwrap();
+        sink.write(Path::new("index.html"), b"<p>broken</p>")
+            .unwrap();
+        drop(sink);
+        assert_eq!(fs::read(root.join("index.html")).unwrap(), b"<p>home</p>");
+
+        // The next successful build replaces it, stale files and all.
+        let mut sink = DirSink::new(&root);
+        sink.begin().unwrap();
+        sink.write(Path::new("index.html"), b"<p>new</p>").unwrap();
+        sink.finish().unwrap();
+        assert_eq!(fs::read(root.join("index.html")).unwrap(), b"<p>new</p>");
+        assert!(!root.join("blog").exists());
+        let mut entries: Vec<_> = fs::read_dir(tmp.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        entries.sort();
+        assert_eq!(entries, ["public"]);
+    }
+
+    #[test]
+    fn failed_swaps_put_the_previous_build_back() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let root = tmp.path().join("public");
+        let old = tmp.path().join(".public.old");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("index.html"), b"<p>home</p>").unwrap();
+
+        // A missing staging directory makes moving it into place fail.
+        let staging = tmp.path().join(".public.staging");
+        assert!(replace_dir(&staging, &root, &old).is_err());
+        assert_eq!(fs::read(root.join("index.html")).unwrap(), b"<p>home</p>");
+        assert!(!old.exists());
+
+        fs::create_dir(&staging).unwrap();
+        fs::write(staging.join("index.html"), b"<p>new</p>").unwrap();
+        replace_dir(&staging, &root, &old).unwrap();
+        assert_eq!(fs::read(root.join("index.html")).unwrap(), b"<p>new</p>");
+        assert_eq!(fs::read(old.join("index.html")).unwrap(), b"<p>home</p>");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn exchange_swaps_directories() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (a, b) = (tmp.path().join("a"), tmp.path().join("b"));
+        fs::create_dir(&a).unwrap();
+        fs::create_dir(&b).unwrap();
+        fs::write(a.join("file"), b"a").unwrap();
+        // Filesystems without RENAME_EXCHANGE fall back to replace_dir.
+        if exchange(&a, &b).is_ok() {
+            assert_eq!(fs::read(b.join("file")).unwrap(), b"a");
+            assert!(!a.join("file").exists());
+        }
+        assert!(exchange(&a, &tmp.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn archive_sinks_contain_every_file() {
+        let tmp = tempfile::TempDir::new().unwrap();
+
+        let tarball = tmp.path().join("site.tar.gz");
+        write_site(sink_for_archive(&tarball).unwrap().as_mut());
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(&tarball).unwrap()));
+        let mut names: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["blog/post/index.html", "index.html"]);
+
+        let zipfile = tmp.path().join("site.zip");
+        write_site(sink_for_archive(&zipfile).unwrap().as_mut());
+        let mut archive = zip::ZipArchive::new(File::open(&zipfile).unwrap()).unwrap();
+        let mut post = String::new();
+        archive
+            .by_name("blog/post/index.html")
+            .unwrap()
+            .read_to_string(&mut post)
+            .unwrap();
+        assert_eq!(post, "<p>post</p>");
+
+        assert!(sink_for_archive(&tmp.path().join("site.rar")).is_err());
+    }
+
+    #[test]
+    fn tarballs_are_reproducible() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let build = |name: &str| {
+            let path = tmp.path().join(name);
+            let mut sink = TarGzSink::new(&path);
+            sink.mtime = 1_700_000_000;
+            write_site(&mut sink);
+            fs::read(path).unwrap()
+        };
+        let first = build("first.tar.gz");
+        assert_eq!(first, build("second.tar.gz"));
+
+        let mut archive = tar::Archive::new(GzDecoder::new(first.as_slice()));
+        for entry in archive.entries().unwrap() {
+            assert_eq!(entry.unwrap().header().mtime().unwrap(), 1_700_000_000);
+        }
+    }
+
+    #[test]
+    fn unfinished_archives_leave_the_previous_one_alone() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        for name in ["site.tar.gz", "site.zip"] {
+            let path = tmp.path().join(name);
+            fs::write(&path, b"previous").unwrap();
+            let mut sink = sink_for_archive(&path).unwrap();
+            sink.begin().unwrap();
+            sink.write(Path::new("index.html"), b"<p>home</p>").unwrap();
+            drop(sink);
+            assert_eq!(fs::read(&path).unwrap(), b"previous");
+        }
+        let mut entries: Vec<_> = fs::read_dir(tmp.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        entries.sort();
+        assert_eq!(entries, ["site.tar.gz", "site.zip"]);
+    }
+
+    #[test]
+    fn archives_reject_duplicate_files() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        for name in ["site.tar.gz", "site.zip"] {
+            let mut sink = sink_for_archive(&tmp.path().join(name)).unwrap();
+            sink.begin().unwrap();
+            sink.write(Path::new("static/colors.css"), b"a").unwrap();
+            let err = sink
+                .write(Path::new("static/colors.css"), b"b")
+                .unwrap_err();
+            assert!(err.to_string().contains("colors.css"), "{}", err);
+        }
+    }
+}
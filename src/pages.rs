@@ -0,0 +1,268 @@
+use crate::blog::parse_org_export;
+use crate::error::SiteError;
+use miette::Result;
+use pulldown_cmark::{CowStr, Event, HeadingLevel, Options, Parser, Tag, TagEnd, html};
+use select::document::Document;
+use select::predicate::Name;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+
+/// ContentPage is a standalone page from the pages directory, e.g. `pages/about.md`
+/// becomes `/about`. Pages are either org-mode html exports or Markdown.
+#[derive(Serialize, Debug, Clone)]
+pub struct ContentPage {
+    pub title: String,
+    pub slug: String,
+    /// toc is the html table of contents, empty if the page has no sections.
+    pub toc: String,
+    /// desc is the first paragraph of the page, if any.
+    pub desc: String,
+    pub html: String,
+    pub footnotes: Vec<String>,
+}
+
+/// get_org_page reads an org-mode html export. Unlike blog articles,
+/// pages don't need a date or a table of contents.
+fn get_org_page(path: &Path, slug: String) -> Result<ContentPage> {
+    let file_contents = fs::read_to_string(path).map_err(SiteError::from)?;
+    let document = Document::from(file_contents.as_str());
+    let export = parse_org_export(path, &document)?;
+
+    let desc = export
+        .sections
+        .iter()
+        .flat_map(|section| section.find(Name("p")))
+        .next()
+        .map(|p| p.text().trim().to_owned())
+        .unwrap_or_default();
+
+    Ok(ContentPage {
+        title: export.title,
+        slug,
+        toc: export.toc.map(|toc| toc.html()).unwrap_or_default(),
+        desc,
+        html: export
+            .sections
+            .iter()
+            .map(|section| section.html())
+            .collect(),
+        footnotes: export.footnotes,
+    })
+}
+
+/// slugify turns text into an html id or url path segment, i.e. "Now & Then" -> "now-then".
+pub(crate) fn slugify(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// toc_html renders nested lists of links to `headings`, in the same shape as
+/// the table of contents org-mode exports.
+fn toc_html(headings: &[(HeadingLevel, String, String)]) -> String {
+    let Some(top) = headings.iter().map(|(level, _, _)| *level).min() else {
+        return String::new();
+    };
+    let mut toc = String::from("<div id=\"text-table-of-contents\">\n<ul>\n");
+    let mut depth = top as usize;
+    for (i, (level, id, text)) in headings.iter().enumerate() {
+        let level = *level as usize;
+        if i > 0 && level <= depth {
+            toc.push_str("</li>\n");
+        }
+        while depth < level {
+            toc.push_str("\n<ul>\n");
+            depth += 1;
+        }
+        while depth > level {
+            toc.push_str("</ul>\n</li>\n");
+            depth -= 1;
+        }
+        toc.push_str(&format!("<li><a href=\"#{}\">{}</a>", id, text));
+    }
+    toc.push_str("</li>\n");
+    while depth > top as usize {
+        toc.push_str("</ul>\n</li>\n");
+        depth -= 1;
+    }
+    toc.push_str("</ul>\n</div>");
+    toc
+}
+
+/// get_markdown_page renders a Markdown page. The first `#` heading is the title,
+/// and every other heading gets an id and an entry in the table of contents.
+fn get_markdown_page(path: &Path, slug: String) -> Result<ContentPage> {
+    let source = fs::read_to_string(path).map_err(SiteError::from)?;
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_HEADING_ATTRIBUTES;
+    let mut events: Vec<Event> = Parser::new_ext(&source, options).collect();
+
+    let mut title = None;
+    let mut headings = Vec::new();
+    let mut ids = HashSet::new();
+    let mut removed = Vec::new();
+    let mut i = 0;
+    while i < events.len() {
+        let Event::Start(Tag::Heading { level, .. }) = events[i] else {
+            i += 1;
+            continue;
+        };
+        let end = (i..events.len())
+            .find(|&j| matches!(events[j], Event::End(TagEnd::Heading(_))))
+            .expect("headings are always closed");
+        let text: String = events[i + 1..end]
+            .iter()
+            .filter_map(|event| match event {
+                Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+                _ => None,
+            })
+            .collect();
+
+        if level == HeadingLevel::H1 && title.is_none() {
+            title = Some(text);
+            removed.push(i..end + 1);
+        } else {
+            let Event::Start(Tag::Heading { id, .. }) = &mut events[i] else {
+                unreachable!()
+            };
+            let mut unique = id.as_deref().map_or_else(|| slugify(&text), str::to_owned);
+            let base = unique.clone();
+            let mut n = 1;
+            while !ids.insert(unique.clone()) {
+                n += 1;
+                unique = format!("{}-{}", base, n);
+            }
+            *id = Some(CowStr::from(unique.clone()));
+
+            let mut label = String::new();
+            html::push_html(&mut label, events[i + 1..end].iter().cloned());
+            headings.push((level, unique, label));
+        }
+        i = end + 1;
+    }
+    for range in removed.into_iter().rev() {
+        events.drain(range);
+    }
+
+    let desc = events
+        .iter()
+        .skip_while(|event| !matches!(event, Event::Start(Tag::Paragraph)))
+        .take_while(|event| !matches!(event, Event::End(TagEnd::Paragraph)))
+        .filter_map(|event| match event {
+            Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+            Event::SoftBreak | Event::HardBreak => Some(" "),
+            _ => None,
+        })
+        .collect();
+
+    let mut body = String::new();
+    html::push_html(&mut body, events.into_iter());
+
+    Ok(ContentPage {
+        title: title.unwrap_or_else(|| slug.clone()),
+        slug,
+        toc: toc_html(&headings),
+        desc,
+        html: body,
+        footnotes: Vec::new(),
+    })
+}
+
+/// get_content_pages reads every page in `pages_root`: org-mode html exports
+/// (`*.html`) and Markdown (`*.md`). Org sources are skipped, like in the blog.
+/// A missing `pages_root` simply means the site has no content pages.
+pub fn get_content_pages(pages_root: impl AsRef<Path>) -> Result<Vec<ContentPage>> {
+    let pages_root = pages_root.as_ref();
+    if !pages_root.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut paths: Vec<_> = fs::read_dir(pages_root)
+        .map_err(SiteError::from)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()
+        .map_err(SiteError::from)?;
+    paths.sort();
+
+    let mut pages: Vec<ContentPage> = Vec::new();
+    for path in paths {
+        let Some(slug) = path.file_stem().and_then(OsStr::to_str) else {
+            continue;
+        };
+        let slug = slugify(slug);
+        let page = match path.extension().and_then(OsStr::to_str) {
+            Some("html") => get_org_page(&path, slug),
+            Some("md") => get_markdown_page(&path, slug),
+            _ => continue,
+        };
+        match page {
+            Ok(page) if pages.iter().any(|p| p.slug == page.slug) => {
+                return Err(SiteError::DuplicatePage(format!("{}/index.html", page.slug)).into());
+            }
+            Ok(page) => pages.push(page),
+            Err(e) => eprintln!("Failed to parse page {:?}: {}", path, e),
+        }
+    }
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn markdown_page(source: &str) -> ContentPage {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("now.md");
+        fs::write(&path, source).unwrap();
+        get_markdown_page(&path, "now".to_owned()).unwrap()
+    }
+
+    #[test]
+    fn markdown_title_toc_and_description() {
+        let page = markdown_page(
+            "# What I'm doing now\n\nWriting a *static* site\ngenerator.\n\n\
+             ## Work\n\ntext\n\n### `rust` & co\n\n## Work\n\n## Custom {#mine}\n",
+        );
+        assert_eq!(page.title, "What I'm doing now");
+        assert_eq!(page.desc, "Writing a static site generator.");
+        assert!(!page.html.contains("<h1>"));
+        assert!(page.html.contains("<h2 id=\"work\">Work</h2>"));
+        assert!(
+            page.html
+                .contains("<h3 id=\"rust-co\"><code>rust</code> &amp; co</h3>")
+        );
+        assert!(page.html.contains("<h2 id=\"work-2\">Work</h2>"));
+        assert!(page.html.contains("<h2 id=\"mine\">Custom</h2>"));
+        assert_eq!(
+            page.toc,
+            "<div id=\"text-table-of-contents\">\n<ul>\n\
+             <li><a href=\"#work\">Work</a>\n<ul>\n\
+             <li><a href=\"#rust-co\"><code>rust</code> &amp; co</a></li>\n</ul>\n</li>\n\
+             <li><a href=\"#work-2\">Work</a></li>\n\
+             <li><a href=\"#mine\">Custom</a></li>\n</ul>\n</div>"
+        );
+    }
+
+    #[test]
+    fn markdown_without_headings() {
+        let page = markdown_page("Just a paragraph.");
+        assert_eq!(page.title, "now");
+        assert_eq!(page.toc, "");
+        assert_eq!(page.desc, "Just a paragraph.");
+    }
+
+    #[test]
+    fn page_slugs_come_from_slugified_file_names() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::write(tmp.path().join("About Me.md"), "# About me\n").unwrap();
+        let pages = get_content_pages(tmp.path()).unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].slug, "about-me");
+    }
+}
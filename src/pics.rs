@@ -1,63 +1,486 @@
+use crate::blog::ParsingError;
+use crate::camera::{CameraInfo, GeoPoint, read_camera_info};
+use crate::cards::{COLLAGE_PHOTOS, Collage, collage_photo};
+use crate::context::strip_html;
 use crate::error::SiteError;
-use chrono::NaiveDate;
+use crate::pages::slugify;
+use crate::placeholder::{Placeholder, decode_photo, make_placeholder, make_thumbnail};
+use chrono::{Datelike, NaiveDate};
 use miette::Result;
-use serde::Serialize;
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, TagEnd, html};
+use pulldown_cmark_escape::{escape_href, escape_html};
+use serde::{Deserialize, Deserializer, Serialize, de};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::OsStr;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, Default)]
 pub struct Photo {
     pub filename: String,
+    /// slug names the photo's page, `/pics/<date>/<slug>/`, and is unique within its session.
+    pub slug: String,
     pub title: Option<String>,
     pub maps_url: Option<String>,
     pub description: Option<String>,
+    /// alt is the image's alt text: the `alt:` key, or else the text of the
+    /// title or description. Empty if the photo has none of them.
+    pub alt: String,
+    /// date is when the photo was taken, if it differs from the session.
+    pub date: Option<NaiveDate>,
+    pub tags: Vec<String>,
+    /// hidden photos are kept with the session but aren't shown, and get no page.
+    pub hidden: bool,
+    /// camera is the camera metadata embedded in the photo, if any.
+    pub camera: Option<CameraInfo>,
+    /// geo is where the photo was taken: the `geo: lat,lon` key, or else its EXIF GPS position.
+    pub geo: Option<GeoPoint>,
+    /// placeholder is shown while the photo loads, if the photo could be read.
+    pub placeholder: Option<Placeholder>,
+    /// thumbnail is shown on pages listing many photos, if the photo could be read.
+    pub thumbnail: Option<Thumbnail>,
+    /// hide_geo is set by `geo: off`, for photos whose location shouldn't be published.
+    #[serde(skip)]
+    pub hide_geo: bool,
+}
+
+/// THUMBNAILS_DIR is where a session's thumbnails are published, under its directory.
+/// It's reserved: pics sessions can't publish a file with the same name.
+pub static THUMBNAILS_DIR: &str = "_thumbs";
+
+/// Thumbnail is a small square copy of a shown photo, made when the photo is loaded.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Thumbnail {
+    /// path is where it's published, relative to its session, i.e. "_thumbs/red-door.jpg".
+    pub path: String,
+    /// jpeg is the encoded thumbnail.
+    #[serde(skip)]
+    pub jpeg: Vec<u8>,
+}
+
+/// SessionFrontMatter is the optional toml block between `+++` lines
+/// at the top of a `PIC.md`:
+///
+/// +++
+/// location = "Toronto Islands"
+/// camera = "Pentax K1000"
+/// film = "Portra 400"
+/// tags = ["film", "summer"]
+/// cover = "ferry.jpg"
+/// date = 2026-07-04
+/// draft = false
+/// +++
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SessionFrontMatter {
+    pub location: Option<String>,
+    pub camera: Option<String>,
+    /// film is the film stock, or the editing preset for digital photos.
+    #[serde(alias = "preset")]
+    pub film: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// cover is the filename of the photo which represents the session.
+    pub cover: Option<String>,
+    /// date is when the session was, for directories not named after their date.
+    /// It wins over the date a directory's name starts with. Templates see the
+    /// resolved [PicSession::date](crate::pics::PicSession::date) instead.
+    #[serde(default, deserialize_with = "toml_date", skip_serializing)]
+    pub date: Option<NaiveDate>,
+    /// draft sessions are left out of the site.
+    #[serde(default)]
+    pub draft: bool,
+}
+
+/// toml_date reads a toml date, i.e. `date = 2026-04-10`, or the same date as a string.
+fn toml_date<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<NaiveDate>, D::Error> {
+    let text = match toml::Value::deserialize(deserializer)? {
+        toml::Value::Datetime(datetime) => datetime.to_string(),
+        toml::Value::String(text) => text,
+        other => {
+            let found = format!("expected a date, found {}", other.type_str());
+            return Err(de::Error::custom(found));
+        }
+    };
+    NaiveDate::parse_from_str(&text, "%Y-%m-%d")
+        .map(Some)
+        .map_err(|_| de::Error::custom(format!("expected a YYYY-MM-DD date, found {}", text)))
 }
 
 #[derive(Serialize, Debug, Clone)]
 pub struct PicSession {
     pub date: NaiveDate,
     pub date_str: String,
+    /// slug names the session's page, `/pics/<slug>/`: its date, then the rest of its
+    /// directory's name, i.e. "2026-04-10-harbour".
+    pub slug: String,
+    /// dir is the directory the session's photos are in.
+    #[serde(skip)]
+    pub dir: PathBuf,
     pub title: String,
     pub description: String,
     pub photos: Vec<Photo>,
+    #[serde(flatten)]
+    pub front_matter: SessionFrontMatter,
+    /// collage is the photos of the session's preview card.
+    #[serde(skip)]
+    pub collage: Collage,
+}
+
+impl PicSession {
+    /// shown_photos returns the photos which aren't hidden, cover first.
+    pub fn shown_photos(&self) -> impl Iterator<Item = &Photo> {
+        let is_cover = |photo: &&Photo| self.front_matter.cover.as_ref() == Some(&photo.filename);
+        let visible = || self.photos.iter().filter(|photo| !photo.hidden);
+        visible()
+            .filter(is_cover)
+            .chain(visible().filter(move |photo| !is_cover(photo)))
+    }
+
+    /// photo_pages returns every photo which gets its own page,
+    /// with the photos before and after it in the session.
+    pub fn photo_pages(&self) -> Vec<(&Photo, Option<&Photo>, Option<&Photo>)> {
+        let visible: Vec<&Photo> = self.photos.iter().filter(|photo| !photo.hidden).collect();
+        (0..visible.len())
+            .map(|i| {
+                let prev = i.checked_sub(1).map(|prev| visible[prev]);
+                (visible[i], prev, visible.get(i + 1).copied())
+            })
+            .collect()
+    }
+}
+
+/// assign_slugs names each photo's page after its file, i.e. "Red Door.jpg" -> "red-door",
+/// numbering photos whose names collide.
+fn assign_slugs(photos: &mut [Photo]) {
+    let mut taken = HashSet::new();
+    for photo in photos {
+        let stem = Path::new(&photo.filename)
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .unwrap_or_default();
+        let base = Some(slugify(stem))
+            .filter(|slug| !slug.is_empty())
+            .unwrap_or_else(|| "photo".to_owned());
+        let mut slug = base.clone();
+        let mut n = 1;
+        while !taken.insert(slug.clone()) {
+            n += 1;
+            slug = format!("{}-{}", base, n);
+        }
+        photo.slug = slug;
+    }
 }
 
 #[derive(Serialize, Debug)]
 pub struct PicsGallery {
     pub sessions: Vec<PicSession>,
+    /// root is the directory the sessions were read from.
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+/// TAGS_URI is where the album of each tag is served from, i.e. `/pics/tags/trees/`.
+pub static TAGS_URI: &str = "/pics/tags";
+
+/// Album collects the photos with a tag across every session, newest session first.
+#[derive(Serialize, Debug)]
+pub struct Album<'a> {
+    /// tag is the tag as first written, i.e. "Trees".
+    pub tag: &'a str,
+    /// slug names the album's page, `/pics/tags/<slug>/`.
+    pub slug: String,
+    pub photos: Vec<AlbumPhoto<'a>>,
 }
 
-fn render_md_links(text: &str) -> String {
-    let mut result = String::with_capacity(text.len());
-    let mut remaining = text;
-    while let Some(bracket_start) = remaining.find('[') {
-        result.push_str(&remaining[..bracket_start]);
-        remaining = &remaining[bracket_start + 1..];
-        if let Some(bracket_end) = remaining.find(']') {
-            let link_text = &remaining[..bracket_end];
-            let after_bracket = &remaining[bracket_end + 1..];
-            if after_bracket.starts_with('(') {
-                if let Some(paren_end) = after_bracket.find(')') {
-                    let url = &after_bracket[1..paren_end];
-                    result.push_str(&format!(
-                        "<a href=\"{url}\" target=\"_blank\">{link_text}</a>"
-                    ));
-                    remaining = &after_bracket[paren_end + 1..];
-                    continue;
+/// AlbumPhoto is a photo in an [Album](crate::pics::Album), with the session it's from.
+#[derive(Serialize, Debug)]
+pub struct AlbumPhoto<'a> {
+    pub date_str: &'a str,
+    pub session_slug: &'a str,
+    pub session_title: &'a str,
+    pub photo: &'a Photo,
+}
+
+impl PicsGallery {
+    /// albums returns an album for every tag, sorted by slug. A session's tags apply to
+    /// all of its photos, and tags which only differ in case or punctuation are the same album.
+    pub fn albums(&self) -> Vec<Album<'_>> {
+        let mut albums: BTreeMap<String, Album> = BTreeMap::new();
+        for session in &self.sessions {
+            for photo in session.photos.iter().filter(|photo| !photo.hidden) {
+                let mut slugs = HashSet::new();
+                for tag in session.front_matter.tags.iter().chain(&photo.tags) {
+                    let slug = slugify(tag);
+                    // Tagging both the session and one of its photos lists the photo once.
+                    if slug.is_empty() || !slugs.insert(slug.clone()) {
+                        continue;
+                    }
+                    let album = albums.entry(slug.clone()).or_insert_with(|| Album {
+                        tag,
+                        slug,
+                        photos: Vec::new(),
+                    });
+                    album.photos.push(AlbumPhoto {
+                        date_str: &session.date_str,
+                        session_slug: &session.slug,
+                        session_title: &session.title,
+                        photo,
+                    });
                 }
             }
-            // Not a valid link — emit literally
-            result.push('[');
-            result.push_str(link_text);
-            result.push(']');
-            remaining = after_bracket;
-        } else {
-            result.push('[');
         }
+        albums.into_values().collect()
+    }
+}
+
+/// MAP_URI is where the map of geotagged photos is served from.
+pub static MAP_URI: &str = "/pics/map";
+
+/// MAP_GEOJSON is the file name of the map's GeoJSON, under [MAP_URI](crate::pics::MAP_URI).
+pub static MAP_GEOJSON: &str = "photos.geojson";
+
+impl PicsGallery {
+    /// geojson returns a GeoJSON `FeatureCollection` with a point for every shown,
+    /// geotagged photo, linking to the photo's page.
+    pub fn geojson(&self) -> serde_json::Value {
+        let features: Vec<serde_json::Value> = self
+            .sessions
+            .iter()
+            .flat_map(|session| session.shown_photos().map(move |photo| (session, photo)))
+            .filter_map(|(session, photo)| {
+                let geo = photo.geo?;
+                let session_uri = format!("/pics/{}/", session.slug);
+                // Markers are small, so they show the thumbnail if there's one.
+                let image = photo
+                    .thumbnail
+                    .as_ref()
+                    .map_or(&photo.filename, |thumbnail| &thumbnail.path);
+                Some(json!({
+                    "type": "Feature",
+                    "geometry": { "type": "Point", "coordinates": [geo.lon, geo.lat] },
+                    "properties": {
+                        "title": photo.alt,
+                        "session": session.title,
+                        "date": photo.date.unwrap_or(session.date).to_string(),
+                        "url": format!("{}{}/", session_uri, photo.slug),
+                        "image": format!("{}{}", session_uri, image),
+                    },
+                }))
+            })
+            .collect();
+        json!({ "type": "FeatureCollection", "features": features })
+    }
+}
+
+/// SESSIONS_PER_PAGE is how many sessions each page of the pics index lists.
+pub const SESSIONS_PER_PAGE: usize = 10;
+
+/// THUMBNAILS_PER_SESSION is how many photos the pics index shows of each session.
+pub const THUMBNAILS_PER_SESSION: usize = 4;
+
+/// SessionPreview is a session as listed on the pics index: its first photos, cover first.
+#[derive(Serialize, Debug)]
+pub struct SessionPreview<'a> {
+    pub session: &'a PicSession,
+    pub thumbnails: Vec<&'a Photo>,
+    /// more is how many shown photos only the session's page has.
+    pub more: usize,
+}
+
+impl<'a> SessionPreview<'a> {
+    fn new(session: &'a PicSession) -> Self {
+        let thumbnails: Vec<&Photo> = session
+            .shown_photos()
+            .take(THUMBNAILS_PER_SESSION)
+            .collect();
+        let more = session.shown_photos().count() - thumbnails.len();
+        SessionPreview {
+            session,
+            thumbnails,
+            more,
+        }
+    }
+}
+
+/// PicsIndex is a page listing sessions, either a page of the pics index,
+/// i.e. `/pics/page/2`, or the archive of a year, i.e. `/pics/2026`.
+#[derive(Serialize, Debug)]
+pub struct PicsIndex<'a> {
+    /// uri is where the page lives.
+    pub uri: String,
+    /// title is i.e. "pics", "pics page 2" or "pics 2026".
+    pub title: String,
+    /// year is the archive's year, which index pages don't have.
+    pub year: Option<i32>,
+    pub sessions: Vec<SessionPreview<'a>>,
+    /// prev and next are the uris of the neighbouring index pages.
+    pub prev: Option<String>,
+    pub next: Option<String>,
+    /// years are the years with sessions, newest first.
+    pub years: Vec<i32>,
+}
+
+impl PicsGallery {
+    /// years returns the years with sessions, newest first.
+    pub fn years(&self) -> Vec<i32> {
+        let mut years: Vec<i32> = self
+            .sessions
+            .iter()
+            .map(|session| session.date.year())
+            .collect();
+        years.sort_by_key(|year| std::cmp::Reverse(*year));
+        years.dedup();
+        years
+    }
+
+    /// index_pages splits the sessions into pages of
+    /// [SESSIONS_PER_PAGE](crate::pics::SESSIONS_PER_PAGE). The first page is `/pics`,
+    /// the rest `/pics/page/<n>`. Without sessions there's one empty page.
+    pub fn index_pages(&self) -> Vec<PicsIndex<'_>> {
+        let sessions: Vec<&PicSession> = self.sessions.iter().collect();
+        self.paginate("/pics", "pics", None, &sessions)
+    }
+
+    /// year_pages returns the archive of every year, newest first, split into pages like
+    /// [index_pages](crate::pics::PicsGallery::index_pages): i.e. `/pics/2026`, then
+    /// `/pics/2026/page/<n>`.
+    pub fn year_pages(&self) -> Vec<PicsIndex<'_>> {
+        self.years()
+            .into_iter()
+            .flat_map(|year| {
+                let sessions: Vec<&PicSession> = self
+                    .sessions
+                    .iter()
+                    .filter(|session| session.date.year() == year)
+                    .collect();
+                let uri = format!("/pics/{}", year);
+                self.paginate(&uri, &format!("pics {}", year), Some(year), &sessions)
+            })
+            .collect()
+    }
+
+    /// paginate splits `sessions` into pages of [SESSIONS_PER_PAGE](crate::pics::SESSIONS_PER_PAGE).
+    /// The first page is `uri` and titled `title`, the rest `<uri>/page/<n>`, i.e. "pics page 2".
+    fn paginate<'a>(
+        &self,
+        uri: &str,
+        title: &str,
+        year: Option<i32>,
+        sessions: &[&'a PicSession],
+    ) -> Vec<PicsIndex<'a>> {
+        let page_uri = |n: usize| match n {
+            1 => uri.to_owned(),
+            n => format!("{}/page/{}", uri, n),
+        };
+        let chunks: Vec<&[&PicSession]> = sessions.chunks(SESSIONS_PER_PAGE).collect();
+        let pages = chunks.len().max(1);
+        (1..=pages)
+            .map(|n| PicsIndex {
+                uri: page_uri(n),
+                title: match n {
+                    1 => title.to_owned(),
+                    n => format!("{} page {}", title, n),
+                },
+                year,
+                sessions: chunks
+                    .get(n - 1)
+                    .map(|chunk| chunk.iter().copied().map(SessionPreview::new).collect())
+                    .unwrap_or_default(),
+                prev: (n > 1).then(|| page_uri(n - 1)),
+                next: (n < pages).then(|| page_uri(n + 1)),
+                years: self.years(),
+            })
+            .collect()
+    }
+}
+
+/// tag_url is the `tag_url` Tera filter, turning a tag into the url of its album:
+///
+/// <a href="{{ tag | tag_url }}">{{ tag }}</a>
+pub fn tag_url(value: &tera::Value, _: &HashMap<String, tera::Value>) -> tera::Result<tera::Value> {
+    let tag = value
+        .as_str()
+        .ok_or_else(|| tera::Error::msg("tag_url expects a string"))?;
+    // PIC.md tags without a slug are dropped when parsing, so they never get an album.
+    let slug = slugify(tag);
+    if slug.is_empty() {
+        return Err(tera::Error::msg(format!(
+            "tag_url: tag {:?} has no album",
+            tag
+        )));
+    }
+    Ok(tera::Value::String(format!("{}/{}/", TAGS_URI, slug)))
+}
+
+/// is_unsafe_url returns whether following `url` would run code, i.e. `javascript:` links.
+fn is_unsafe_url(url: &str) -> bool {
+    let scheme = url.trim_start().split(':').next().unwrap_or_default();
+    url.contains(':')
+        && ["javascript", "vbscript", "data"]
+            .iter()
+            .any(|unsafe_scheme| scheme.eq_ignore_ascii_case(unsafe_scheme))
+}
+
+/// render_markdown renders `PIC.md` text as CommonMark. Raw html is escaped
+/// rather than passed through, and links open in a new tab.
+fn render_markdown(text: &str) -> String {
+    let mut in_unsafe_link = false;
+    let events = Parser::new_ext(text, Options::ENABLE_STRIKETHROUGH).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        Event::Start(Tag::Link { dest_url, .. }) if is_unsafe_url(&dest_url) => {
+            in_unsafe_link = true;
+            Event::InlineHtml(CowStr::Borrowed(""))
+        }
+        Event::End(TagEnd::Link) if in_unsafe_link => {
+            in_unsafe_link = false;
+            Event::InlineHtml(CowStr::Borrowed(""))
+        }
+        Event::Start(Tag::Link {
+            dest_url, title, ..
+        }) => {
+            let mut a = String::from("<a href=\"");
+            escape_href(&mut a, &dest_url).expect("writing to a String can't fail");
+            if !title.is_empty() {
+                a.push_str("\" title=\"");
+                escape_html(&mut a, &title).expect("writing to a String can't fail");
+            }
+            a.push_str("\" target=\"_blank\">");
+            Event::InlineHtml(a.into())
+        }
+        Event::End(TagEnd::Link) => Event::InlineHtml(CowStr::Borrowed("</a>")),
+        event => event,
+    });
+    let mut rendered = String::with_capacity(text.len());
+    html::push_html(&mut rendered, events);
+    rendered.trim_end().to_owned()
+}
+
+/// render_inline_markdown renders a single line, like a photo title,
+/// without wrapping it in a paragraph. Markers which would start a block,
+/// like the `1.` in "1. Start" or the `##` in "## Day one", are kept as text.
+fn render_inline_markdown(text: &str) -> String {
+    let escaped;
+    let text = match Parser::new(text).next() {
+        None | Some(Event::Start(Tag::Paragraph)) => text,
+        Some(_) => {
+            // A backslash makes the marker literal, i.e. `1\. Start` or `\## Day one`.
+            let marker = text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            escaped = format!("{}\\{}", &text[..marker], &text[marker..]);
+            &escaped
+        }
+    };
+    let rendered = render_markdown(text);
+    match rendered
+        .strip_prefix("<p>")
+        .and_then(|inner| inner.strip_suffix("</p>"))
+    {
+        Some(inner) if !inner.contains("<p>") => inner.to_owned(),
+        _ => rendered,
     }
-    result.push_str(remaining);
-    result
 }
 
 fn is_image_ext(path: &str) -> bool {
@@ -66,7 +489,78 @@ fn is_image_ext(path: &str) -> bool {
         .and_then(OsStr::to_str)
         .unwrap_or("")
         .to_lowercase();
-    matches!(ext.as_str(), "jpg" | "jpeg" | "png" | "webp" | "gif" | "avif")
+    matches!(
+        ext.as_str(),
+        "jpg" | "jpeg" | "png" | "webp" | "gif" | "avif"
+    )
+}
+
+/// is_valid_tag returns whether `tag` can have an album, warning about it if not:
+/// tags without letters or digits have no slug to serve the album from.
+fn is_valid_tag(tag: &str) -> bool {
+    let valid = !slugify(tag).is_empty();
+    if !valid {
+        eprintln!(
+            "Warning: tag {:?} has no letters or digits, ignoring it",
+            tag
+        );
+    }
+    valid
+}
+
+/// parse_tags splits a comma-separated `tags:` value, i.e. "film, night" -> ["film", "night"].
+fn parse_tags(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty() && is_valid_tag(tag))
+        .map(str::to_owned)
+        .collect()
+}
+
+/// cover_problem tells why the `cover` of a session in `dir` can't be used, if it can't:
+/// covers must be one of the session's shown photos.
+fn cover_problem(cover: &str, photos: &[Photo], dir: &Path) -> Option<&'static str> {
+    match photos.iter().find(|photo| photo.filename == cover) {
+        None => Some("isn't a photo in PIC.md"),
+        Some(photo) if photo.hidden => Some("is hidden"),
+        Some(_) if !dir.join(cover).is_file() => Some("is missing"),
+        Some(_) => None,
+    }
+}
+
+/// split_front_matter splits the [SessionFrontMatter](crate::pics::SessionFrontMatter)
+/// off the top of a `PIC.md`, returning it and the rest of the file.
+fn split_front_matter<'a>(path: &Path, contents: &'a str) -> Result<(SessionFrontMatter, &'a str)> {
+    let invalid = |reason: String| ParsingError::InvalidFrontMatter(path.to_path_buf(), reason);
+    let Some(rest) = contents
+        .strip_prefix("+++\n")
+        .or_else(|| contents.strip_prefix("+++\r\n"))
+    else {
+        return Ok((SessionFrontMatter::default(), contents));
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "+++" {
+            let front_matter =
+                toml::from_str(&rest[..offset]).map_err(|e| invalid(e.message().to_owned()))?;
+            return Ok((front_matter, &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    Err(invalid("missing closing +++".to_owned()).into())
+}
+
+/// split_session_dir splits a session directory's name into the date it starts with,
+/// if any, and the rest, i.e. "2026-04-10-harbour" is 2026-04-10 and "harbour".
+fn split_session_dir(name: &str) -> (Option<NaiveDate>, &str) {
+    let date = name
+        .get(..10)
+        .and_then(|prefix| NaiveDate::parse_from_str(prefix, "%Y-%m-%d").ok());
+    let rest = name.get(10..).unwrap_or_default();
+    match date {
+        Some(date) if rest.is_empty() || rest.starts_with(['-', '_', ' ']) => (Some(date), rest),
+        _ => (None, name),
+    }
 }
 
 fn parse_pic_md(contents: &str) -> (String, String, Vec<Photo>) {
@@ -75,32 +569,27 @@ fn parse_pic_md(contents: &str) -> (String, String, Vec<Photo>) {
     let mut photos: Vec<Photo> = Vec::new();
 
     // Current photo block being built
-    let mut current_title: Option<String> = None;
-    let mut current_filename: Option<String> = None;
-    let mut current_maps: Option<String> = None;
+    let mut current: Option<Photo> = None;
     let mut current_desc_lines: Vec<&str> = Vec::new();
-    let mut in_photo_block = false;
 
     let flush_photo = |photos: &mut Vec<Photo>,
-                       current_title: &mut Option<String>,
-                       current_filename: &mut Option<String>,
-                       current_maps: &mut Option<String>,
+                       current: &mut Option<Photo>,
                        current_desc_lines: &mut Vec<&str>| {
-        if let Some(filename) = current_filename.take() {
-            let description = {
-                let s = current_desc_lines.join("\n").trim().to_owned();
-                if s.is_empty() { None } else { Some(render_md_links(&s)) }
-            };
-            photos.push(Photo {
-                filename,
-                title: current_title.take().map(|t| render_md_links(&t)),
-                maps_url: current_maps.take(),
-                description,
-            });
-        } else {
-            // No file: line yet — discard partial block
-            current_title.take();
-            current_maps.take();
+        // No file: line yet — discard partial block
+        if let Some(mut photo) = current.take().filter(|photo| !photo.filename.is_empty()) {
+            let description = current_desc_lines.join("\n").trim().to_owned();
+            if !description.is_empty() {
+                photo.description = Some(render_markdown(&description));
+            }
+            if photo.alt.is_empty() {
+                photo.alt = [&photo.title, &photo.description]
+                    .into_iter()
+                    .flatten()
+                    .map(|html| strip_html(html))
+                    .find(|text| !text.is_empty())
+                    .unwrap_or_default();
+            }
+            photos.push(photo);
         }
         current_desc_lines.clear();
     };
@@ -111,32 +600,51 @@ fn parse_pic_md(contents: &str) -> (String, String, Vec<Photo>) {
             session_title = rest.trim().to_owned();
         } else if let Some(rest) = line.strip_prefix("## ") {
             // Start of a new photo block — flush any previous
-            if in_photo_block {
-                flush_photo(
-                    &mut photos,
-                    &mut current_title,
-                    &mut current_filename,
-                    &mut current_maps,
-                    &mut current_desc_lines,
-                );
-            }
-            in_photo_block = true;
-            current_title = Some(rest.trim().to_owned());
-            current_filename = None;
-            current_maps = None;
-            current_desc_lines.clear();
-        } else if in_photo_block {
+            flush_photo(&mut photos, &mut current, &mut current_desc_lines);
+            current = Some(Photo {
+                title: Some(render_inline_markdown(rest.trim())),
+                ..Photo::default()
+            });
+        } else if let Some(photo) = current.as_mut() {
             if let Some(rest) = line.strip_prefix("file:") {
-                current_filename = Some(rest.trim().to_owned());
+                photo.filename = rest.trim().to_owned();
             } else if let Some(rest) = line.strip_prefix("maps:") {
                 let url = rest.trim();
                 if !url.is_empty() {
-                    current_maps = Some(url.to_owned());
+                    photo.maps_url = Some(url.to_owned());
+                }
+            } else if let Some(rest) = line.strip_prefix("alt:") {
+                photo.alt = rest.trim().to_owned();
+            } else if let Some(rest) = line.strip_prefix("date:") {
+                match NaiveDate::parse_from_str(rest.trim(), "%Y-%m-%d") {
+                    Ok(date) => photo.date = Some(date),
+                    Err(_) => eprintln!(
+                        "Warning: cannot parse date {:?} of photo {:?}, ignoring it",
+                        rest.trim(),
+                        photo.filename
+                    ),
+                }
+            } else if let Some(rest) = line.strip_prefix("geo:") {
+                if rest.trim() == "off" {
+                    photo.hide_geo = true;
+                } else if let Some(geo) = GeoPoint::parse(rest) {
+                    photo.geo = Some(geo);
+                } else {
+                    eprintln!(
+                        "Warning: cannot parse geo {:?} of photo {:?}, expected `lat,lon`",
+                        rest.trim(),
+                        photo.filename
+                    );
                 }
+            } else if let Some(rest) = line.strip_prefix("tags:") {
+                photo.tags = parse_tags(rest);
+            } else if let Some(rest) = line.strip_prefix("hidden:") {
+                photo.hidden = rest.trim() == "true";
             } else if is_image_ext(line.trim()) {
                 // Bare filename with no key prefix
-                current_filename = Some(line.trim().to_owned());
-            } else if !line.trim().is_empty() {
+                photo.filename = line.trim().to_owned();
+            } else {
+                // Blank lines are kept, as they separate Markdown paragraphs.
                 current_desc_lines.push(line);
             }
         } else {
@@ -147,53 +655,243 @@ fn parse_pic_md(contents: &str) -> (String, String, Vec<Photo>) {
         }
     }
 
-    if in_photo_block {
-        flush_photo(
-            &mut photos,
-            &mut current_title,
-            &mut current_filename,
-            &mut current_maps,
-            &mut current_desc_lines,
-        );
-    }
+    flush_photo(&mut photos, &mut current, &mut current_desc_lines);
 
-    let session_description = render_md_links(
-        session_description_lines.join("\n").trim()
-    );
+    let session_description = render_markdown(session_description_lines.join("\n").trim());
 
     (session_title, session_description, photos)
 }
 
+pub fn get_pics_gallery(pics_root: impl AsRef<Path>) -> Result<PicsGallery> {
+    let base = pics_root.as_ref().to_path_buf();
+    if !base.is_dir() {
+        return Ok(PicsGallery {
+            sessions: vec![],
+            root: base,
+        });
+    }
+
+    let mut sessions: Vec<PicSession> = Vec::new();
+
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(&base).map_err(SiteError::from)? {
+        let path = entry.map_err(SiteError::from)?.path();
+        if path.is_dir() {
+            dirs.push(path);
+        }
+    }
+    dirs.sort();
+
+    for path in dirs {
+        let Some(dir_name) = path.file_name().and_then(OsStr::to_str).map(str::to_owned) else {
+            eprintln!(
+                "Warning: pics directory {:?} isn't valid UTF-8, skipping",
+                path
+            );
+            continue;
+        };
+        if dir_name.starts_with('.') {
+            continue;
+        }
+
+        let pic_md_path = path.join("PIC.md");
+        if !pic_md_path.exists() {
+            eprintln!("Warning: no PIC.md found in {:?}, skipping", path);
+            continue;
+        }
+
+        let contents = fs::read_to_string(&pic_md_path).map_err(SiteError::from)?;
+        let (mut front_matter, contents) = match split_front_matter(&pic_md_path, &contents) {
+            Ok(split) => split,
+            Err(e) => {
+                eprintln!("Failed to parse pics session {:?}: {}", pic_md_path, e);
+                continue;
+            }
+        };
+        front_matter.tags.retain(|tag| is_valid_tag(tag));
+        if front_matter.draft {
+            println!("Skipping draft pics session {:?}", pic_md_path);
+            continue;
+        }
+        let (dir_date, dir_rest) = split_session_dir(&dir_name);
+        let Some(date) = front_matter.date.or(dir_date) else {
+            eprintln!(
+                "Warning: pics directory {:?} doesn't start with a YYYY-MM-DD date and its \
+                 PIC.md has no `date`, skipping",
+                path
+            );
+            continue;
+        };
+        let slug = match slugify(dir_rest) {
+            rest if rest.is_empty() => date.to_string(),
+            rest => format!("{}-{}", date, rest),
+        };
+        if let Some(other) = sessions.iter().find(|session| session.slug == slug) {
+            eprintln!(
+                "Warning: pics directory {:?} would be served from /pics/{}/ like {:?}, skipping",
+                path, slug, other.dir
+            );
+            continue;
+        }
+
+        let (title, description, mut photos) = parse_pic_md(contents);
+        assign_slugs(&mut photos);
+        // Each shown photo is read and decoded once, cover first like
+        // shown_photos, so the first few decodes make the collage.
+        let cover = front_matter.cover.as_ref();
+        let mut shown: Vec<&mut Photo> = photos.iter_mut().filter(|photo| !photo.hidden).collect();
+        shown.sort_by_key(|photo| Some(&photo.filename) != cover);
+        let mut collage_photos = Vec::new();
+        for photo in shown {
+            let bytes = fs::read(path.join(&photo.filename)).ok();
+            photo.camera = bytes.as_deref().and_then(read_camera_info);
+            let img = bytes.as_deref().and_then(decode_photo);
+            photo.placeholder = img.as_ref().and_then(make_placeholder);
+            photo.thumbnail = img.as_ref().and_then(make_thumbnail).map(|jpeg| Thumbnail {
+                path: format!("{}/{}.jpg", THUMBNAILS_DIR, photo.slug),
+                jpeg,
+            });
+            if let Some(img) = &img
+                && collage_photos.len() < COLLAGE_PHOTOS
+            {
+                collage_photos.push(collage_photo(img));
+            }
+            if let Some(camera) = photo.camera.as_mut() {
+                if photo.hide_geo {
+                    camera.gps = None;
+                }
+                photo.geo = photo.geo.or(camera.gps);
+            }
+            if photo.hide_geo {
+                photo.geo = None;
+            }
+        }
+        if let Some(cover) = &front_matter.cover
+            && let Some(problem) = cover_problem(cover, &photos, &path)
+        {
+            eprintln!(
+                "Warning: cover {} of {:?} {}, ignoring it",
+                cover, pic_md_path, problem
+            );
+        }
+        for photo in photos
+            .iter()
+            .filter(|photo| !photo.hidden && photo.placeholder.is_none())
+        {
+            eprintln!(
+                "Warning: cannot read photo {} in {:?}, it's left out of the preview card",
+                photo.filename, path
+            );
+        }
+        for photo in photos
+            .iter()
+            .filter(|photo| !photo.hidden && photo.alt.is_empty())
+        {
+            eprintln!(
+                "Warning: photo {} in {:?} has no alt text, add an `alt:` line or a title",
+                photo.filename, pic_md_path
+            );
+        }
+
+        println!("Successfully parsed pics session {:?}", pic_md_path);
+
+        sessions.push(PicSession {
+            date,
+            date_str: date.to_string(),
+            slug,
+            dir: path,
+            title,
+            description,
+            photos,
+            front_matter,
+            collage: Collage::from_photos(&collage_photos),
+        });
+    }
+
+    // Sessions on the same day keep the order of their directories.
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.date));
+
+    Ok(PicsGallery {
+        sessions,
+        root: base,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn md_links_converts_link() {
+    fn markdown_converts_link() {
         assert_eq!(
-            render_md_links("see [Foo](https://example.com) here"),
-            "see <a href=\"https://example.com\" target=\"_blank\">Foo</a> here"
+            render_markdown("see [Foo](https://example.com) here"),
+            "<p>see <a href=\"https://example.com\" target=\"_blank\">Foo</a> here</p>"
         );
     }
 
     #[test]
-    fn md_links_multiple_links() {
-        let out = render_md_links("[A](https://a.com) and [B](https://b.com)");
-        assert!(out.contains("<a href=\"https://a.com\""));
-        assert!(out.contains("<a href=\"https://b.com\""));
+    fn markdown_multiple_links() {
+        let out = render_markdown("[A](https://a.com) and [B](https://b.com \"b&b\")");
+        assert!(out.contains("<a href=\"https://a.com\" target=\"_blank\">"));
+        assert!(out.contains("<a href=\"https://b.com\" title=\"b&amp;b\" target=\"_blank\">"));
+    }
+
+    #[test]
+    fn markdown_plain_text_is_a_paragraph() {
+        assert_eq!(render_markdown("plain text"), "<p>plain text</p>");
+    }
+
+    #[test]
+    fn markdown_malformed_links_not_converted() {
+        assert_eq!(render_markdown("[text](no-close"), "<p>[text](no-close</p>");
+        assert_eq!(render_markdown("[text] stuff"), "<p>[text] stuff</p>");
+    }
+
+    #[test]
+    fn markdown_renders_emphasis_code_and_lists() {
+        let out = render_markdown("Shot on *film*, see `roll-3`.\n\n- one\n- two");
+        assert_eq!(
+            out,
+            "<p>Shot on <em>film</em>, see <code>roll-3</code>.</p>\n<ul>\n<li>one</li>\n<li>two</li>\n</ul>"
+        );
+    }
+
+    #[test]
+    fn markdown_escapes_raw_html_and_unsafe_links() {
+        assert_eq!(
+            render_markdown("a < b & <script>alert(1)</script>"),
+            "<p>a &lt; b &amp; &lt;script&gt;alert(1)&lt;/script&gt;</p>"
+        );
+        assert_eq!(
+            render_markdown("<div onclick=\"x\">hi</div>"),
+            "&lt;div onclick=\"x\"&gt;hi&lt;/div&gt;"
+        );
+        assert_eq!(
+            render_markdown("[click](javascript:alert(1))"),
+            "<p>click</p>"
+        );
     }
 
     #[test]
-    fn md_links_no_links_unchanged() {
-        assert_eq!(render_md_links("plain text"), "plain text");
+    fn inline_markdown_has_no_paragraph() {
+        assert_eq!(
+            render_inline_markdown("The *red* door"),
+            "The <em>red</em> door"
+        );
+        assert_eq!(render_inline_markdown("Fish & chips"), "Fish &amp; chips");
     }
 
     #[test]
-    fn md_links_malformed_not_converted() {
-        // Missing closing paren — emitted literally
-        assert_eq!(render_md_links("[text](no-close"), "[text](no-close");
-        // No parens after bracket — emitted literally
-        assert_eq!(render_md_links("[text] stuff"), "[text] stuff");
+    fn inline_markdown_has_no_blocks() {
+        assert_eq!(render_inline_markdown("1. Start"), "1. Start");
+        assert_eq!(render_inline_markdown("## Day one"), "## Day one");
+        assert_eq!(
+            render_inline_markdown("- *left* behind"),
+            "- <em>left</em> behind"
+        );
+        assert_eq!(render_inline_markdown("> quoted"), "&gt; quoted");
+        assert_eq!(render_inline_markdown("---"), "---");
+        assert_eq!(render_inline_markdown("1989"), "1989");
     }
 
     #[test]
@@ -205,7 +903,7 @@ mod tests {
     #[test]
     fn parses_session_description() {
         let (_, desc, _) = parse_pic_md("# Title\n\nA lovely day out.\n\n## Photo\nfile: a.jpg\n");
-        assert_eq!(desc, "A lovely day out.");
+        assert_eq!(desc, "<p>A lovely day out.</p>");
     }
 
     #[test]
@@ -220,14 +918,20 @@ mod tests {
     fn parses_maps_url() {
         let input = "# T\n\n## P\nfile: x.jpg\nmaps: https://maps.google.com/?q=foo\n";
         let (_, _, photos) = parse_pic_md(input);
-        assert_eq!(photos[0].maps_url.as_deref(), Some("https://maps.google.com/?q=foo"));
+        assert_eq!(
+            photos[0].maps_url.as_deref(),
+            Some("https://maps.google.com/?q=foo")
+        );
     }
 
     #[test]
     fn parses_photo_description() {
         let input = "# T\n\n## P\nfile: x.jpg\n\nThis is a nice spot.\n";
         let (_, _, photos) = parse_pic_md(input);
-        assert_eq!(photos[0].description.as_deref(), Some("This is a nice spot."));
+        assert_eq!(
+            photos[0].description.as_deref(),
+            Some("<p>This is a nice spot.</p>")
+        );
     }
 
     #[test]
@@ -237,6 +941,227 @@ mod tests {
         assert!(photos[0].description.is_none());
     }
 
+    #[test]
+    fn photo_pages_have_unique_slugs_and_skip_hidden_photos() {
+        let input = "# T\n\n## A\nfile: Red Door.jpg\n\n## B\nfile: red-door.png\n\n\
+                     ## C\nfile: c.jpg\nhidden: true\n\n## D\nfile: _.jpg\n";
+        let (_, _, mut photos) = parse_pic_md(input);
+        assign_slugs(&mut photos);
+        let slugs: Vec<&str> = photos.iter().map(|photo| photo.slug.as_str()).collect();
+        assert_eq!(slugs, ["red-door", "red-door-2", "c", "photo"]);
+
+        let session = PicSession {
+            date: NaiveDate::from_ymd_opt(2026, 4, 10).unwrap(),
+            date_str: "2026-04-10".to_owned(),
+            slug: "2026-04-10".to_owned(),
+            dir: PathBuf::new(),
+            title: "T".to_owned(),
+            description: String::new(),
+            photos,
+            front_matter: SessionFrontMatter::default(),
+            collage: Collage::default(),
+        };
+        let slug = |photo: Option<&Photo>| photo.map(|photo| photo.slug.clone());
+        let pages: Vec<_> = session
+            .photo_pages()
+            .into_iter()
+            .map(|(photo, prev, next)| (photo.slug.clone(), slug(prev), slug(next)))
+            .collect();
+        let page = |slug: &str, prev: Option<&str>, next: Option<&str>| {
+            (
+                slug.to_owned(),
+                prev.map(str::to_owned),
+                next.map(str::to_owned),
+            )
+        };
+        assert_eq!(
+            pages,
+            [
+                page("red-door", None, Some("red-door-2")),
+                page("red-door-2", Some("red-door"), Some("photo")),
+                page("photo", Some("red-door-2"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn albums_collect_tagged_photos_across_sessions() {
+        let session = |date_str: &str, tags: &[&str], photos: Vec<Photo>| PicSession {
+            date: NaiveDate::parse_from_str(date_str, "%Y-%m-%d").unwrap(),
+            date_str: date_str.to_owned(),
+            slug: date_str.to_owned(),
+            dir: PathBuf::new(),
+            title: date_str.to_owned(),
+            description: String::new(),
+            photos,
+            front_matter: SessionFrontMatter {
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                ..SessionFrontMatter::default()
+            },
+            collage: Collage::default(),
+        };
+        let photo = |filename: &str, tags: &[&str], hidden: bool| Photo {
+            filename: filename.to_owned(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            hidden,
+            ..Photo::default()
+        };
+        let gallery = PicsGallery {
+            sessions: vec![
+                session(
+                    "2026-04-11",
+                    &["Trees"],
+                    vec![photo("a.jpg", &["trees"], false), photo("b.jpg", &[], true)],
+                ),
+                session(
+                    "2026-04-10",
+                    &[],
+                    vec![photo("c.jpg", &["Trees", "Old Town"], false)],
+                ),
+            ],
+            root: PathBuf::new(),
+        };
+        let albums = gallery.albums();
+        let summary: Vec<(&str, &str, Vec<&str>)> = albums
+            .iter()
+            .map(|album| {
+                let photos = album.photos.iter().map(|item| item.photo.filename.as_str());
+                (album.slug.as_str(), album.tag, photos.collect())
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("old-town", "Old Town", vec!["c.jpg"]),
+                ("trees", "Trees", vec!["a.jpg", "c.jpg"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn index_pages_and_year_archives_preview_sessions() {
+        let session = |date_str: &str, photos: usize| PicSession {
+            date: NaiveDate::parse_from_str(date_str, "%Y-%m-%d").unwrap(),
+            date_str: date_str.to_owned(),
+            slug: date_str.to_owned(),
+            dir: PathBuf::new(),
+            title: date_str.to_owned(),
+            description: String::new(),
+            photos: (0..photos)
+                .map(|i| Photo {
+                    filename: format!("{}.jpg", i),
+                    ..Photo::default()
+                })
+                .collect(),
+            front_matter: SessionFrontMatter::default(),
+            collage: Collage::default(),
+        };
+        let mut sessions: Vec<PicSession> = (1..=SESSIONS_PER_PAGE + 1)
+            .map(|day| session(&format!("2026-01-{:02}", day), 1))
+            .rev()
+            .collect();
+        sessions.push(session("2025-12-31", THUMBNAILS_PER_SESSION + 2));
+        let gallery = PicsGallery {
+            sessions,
+            root: PathBuf::new(),
+        };
+
+        let pages = gallery.index_pages();
+        let summary: Vec<_> = pages
+            .iter()
+            .map(|page| {
+                let (prev, next) = (page.prev.as_deref(), page.next.as_deref());
+                (page.uri.as_str(), page.sessions.len(), prev, next)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("/pics", SESSIONS_PER_PAGE, None, Some("/pics/page/2")),
+                ("/pics/page/2", 2, Some("/pics"), None),
+            ]
+        );
+        let last = &pages[1].sessions[1];
+        assert_eq!(
+            (last.thumbnails.len(), last.more),
+            (THUMBNAILS_PER_SESSION, 2)
+        );
+
+        assert_eq!(gallery.years(), [2026, 2025]);
+        let years = gallery.year_pages();
+        let summary: Vec<_> = years
+            .iter()
+            .map(|page| {
+                let (prev, next) = (page.prev.as_deref(), page.next.as_deref());
+                (
+                    page.uri.as_str(),
+                    page.year,
+                    page.sessions.len(),
+                    prev,
+                    next,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "/pics/2026",
+                    Some(2026),
+                    SESSIONS_PER_PAGE,
+                    None,
+                    Some("/pics/2026/page/2")
+                ),
+                ("/pics/2026/page/2", Some(2026), 1, Some("/pics/2026"), None),
+                ("/pics/2025", Some(2025), 1, None, None),
+            ]
+        );
+        assert_eq!(years[1].title, "pics 2026 page 2");
+
+        let empty = PicsGallery {
+            sessions: vec![],
+            root: PathBuf::new(),
+        };
+        assert_eq!(empty.index_pages().len(), 1);
+        assert!(empty.year_pages().is_empty());
+    }
+
+    #[test]
+    fn geo_key_overrides_and_hides_positions() {
+        let input = "# T\n\n## A\nfile: a.jpg\ngeo: 43.6426, -79.3871\n\n\
+                     ## B\nfile: b.jpg\ngeo: somewhere\n\n## C\nfile: c.jpg\ngeo: off\n";
+        let (_, _, mut photos) = parse_pic_md(input);
+        assert_eq!(photos[0].geo, GeoPoint::new(43.6426, -79.3871));
+        assert_eq!(photos[1].geo, None);
+        assert!(photos[2].hide_geo);
+
+        assign_slugs(&mut photos);
+        photos[1].hidden = true;
+        photos[1].geo = GeoPoint::new(1.0, 2.0);
+        let gallery = PicsGallery {
+            sessions: vec![PicSession {
+                date: NaiveDate::from_ymd_opt(2026, 4, 10).unwrap(),
+                date_str: "2026-04-10".to_owned(),
+                slug: "2026-04-10".to_owned(),
+                dir: PathBuf::new(),
+                title: "Harbour".to_owned(),
+                description: String::new(),
+                photos,
+                front_matter: SessionFrontMatter::default(),
+                collage: Collage::default(),
+            }],
+            root: PathBuf::new(),
+        };
+        let geojson = gallery.geojson();
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), 1);
+        assert_eq!(
+            features[0]["geometry"]["coordinates"],
+            json!([-79.3871, 43.6426])
+        );
+        assert_eq!(features[0]["properties"]["url"], "/pics/2026-04-10/a/");
+    }
+
     #[test]
     fn parses_multiple_photos() {
         let input = "# T\n\n## First\nfile: a.jpg\n\n## Second\nfile: b.jpg\n";
@@ -259,6 +1184,205 @@ mod tests {
         assert_eq!(photos.len(), 0);
     }
 
+    #[test]
+    fn parses_photo_keys() {
+        let input = "# T\n\n## P\nfile: x.jpg\nalt: A red door\ndate: 2026-04-09\n\
+                     tags: film, night ,\nhidden: true\n\n## Q\nfile: y.jpg\ndate: yesterday\n";
+        let (_, _, photos) = parse_pic_md(input);
+        assert_eq!(photos[0].alt, "A red door");
+        assert_eq!(photos[0].date, NaiveDate::from_ymd_opt(2026, 4, 9));
+        assert_eq!(photos[0].tags, ["film", "night"]);
+        assert!(photos[0].hidden);
+        assert_eq!(photos[1].date, None);
+        assert!(!photos[1].hidden);
+    }
+
+    #[test]
+    fn alt_text_falls_back_to_title_then_description() {
+        let input = "# T\n\n## The *red* door\nfile: a.jpg\n\n\
+                     ## \nfile: b.jpg\n\nA [blue](https://example.com) door.\n\n\
+                     ## \nfile: c.jpg\n";
+        let (_, _, photos) = parse_pic_md(input);
+        let alts: Vec<&str> = photos.iter().map(|photo| photo.alt.as_str()).collect();
+        assert_eq!(alts, ["The red door", "A blue door.", ""]);
+    }
+
+    #[test]
+    fn front_matter_is_optional() {
+        let path = Path::new("PIC.md");
+        let (front_matter, rest) = split_front_matter(path, "# T\n").unwrap();
+        assert!(front_matter.tags.is_empty() && !front_matter.draft);
+        assert_eq!(rest, "# T\n");
+
+        let input = "+++\nlocation = \"Harbour\"\npreset = \"Classic Chrome\"\n\
+                     tags = [\"film\"]\ncover = \"b.jpg\"\n+++\n# T\n";
+        let (front_matter, rest) = split_front_matter(path, input).unwrap();
+        assert_eq!(front_matter.location.as_deref(), Some("Harbour"));
+        assert_eq!(front_matter.film.as_deref(), Some("Classic Chrome"));
+        assert_eq!(front_matter.tags, ["film"]);
+        assert_eq!(front_matter.cover.as_deref(), Some("b.jpg"));
+        assert_eq!(rest, "# T\n");
+
+        assert!(split_front_matter(path, "+++\ncamera = \"x\"\n# T\n").is_err());
+        assert!(split_front_matter(path, "+++\ncamra = \"x\"\n+++\n").is_err());
+    }
+
+    #[test]
+    fn drafts_are_skipped_and_cover_is_shown_first() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        for (dir, pic_md) in [
+            (
+                "2026-04-10",
+                "+++\ncover = \"b.jpg\"\n+++\n# Shown\n\n## A\nfile: a.jpg\n\n\
+                            ## B\nfile: b.jpg\n\n## C\nfile: c.jpg\nhidden: true\n",
+            ),
+            ("2026-04-11", "+++\ndraft = true\n+++\n# Draft\n"),
+        ] {
+            fs::create_dir(tmp.path().join(dir)).unwrap();
+            fs::write(tmp.path().join(dir).join("PIC.md"), pic_md).unwrap();
+        }
+        let gallery = get_pics_gallery(tmp.path()).unwrap();
+        assert_eq!(gallery.sessions.len(), 1);
+        let session = &gallery.sessions[0];
+        assert_eq!(session.title, "Shown");
+        assert_eq!(session.photos.len(), 3);
+        let shown: Vec<&str> = session
+            .shown_photos()
+            .map(|p| p.filename.as_str())
+            .collect();
+        assert_eq!(shown, ["b.jpg", "a.jpg"]);
+    }
+
+    #[test]
+    fn shown_photos_are_read_once_into_placeholders_and_the_collage() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dir = tmp.path().join("2026-04-10");
+        fs::create_dir(&dir).unwrap();
+        fs::write(
+            dir.join("PIC.md"),
+            "+++\ncover = \"b.jpg\"\n+++\n# Colours\n\n## A\nfile: a.jpg\n\n\
+             ## B\nfile: b.jpg\n\n## C\nfile: c.jpg\nhidden: true\n",
+        )
+        .unwrap();
+        for (file, colour) in [
+            ("a.jpg", [255, 0, 0]),
+            ("b.jpg", [0, 0, 255]),
+            ("c.jpg", [0, 255, 0]),
+        ] {
+            image::RgbImage::from_pixel(32, 32, image::Rgb(colour))
+                .save(dir.join(file))
+                .unwrap();
+        }
+
+        let gallery = get_pics_gallery(tmp.path()).unwrap();
+        let session = &gallery.sessions[0];
+        let placeholders: Vec<bool> = session
+            .photos
+            .iter()
+            .map(|photo| photo.placeholder.is_some())
+            .collect();
+        assert_eq!(placeholders, [true, true, false]);
+        // The cover leads the collage, and hidden photos are left out of it.
+        let cells = &session.collage.cells;
+        assert_eq!(cells.len(), 2);
+        let blue = cells[0].get_pixel(0, 0);
+        assert!(blue[2] > 200 && blue[0] < 50, "{:?}", blue);
+    }
+
+    #[test]
+    fn session_directories_are_named_after_their_date() {
+        let date = NaiveDate::from_ymd_opt(2026, 4, 10);
+        assert_eq!(split_session_dir("2026-04-10"), (date, ""));
+        assert_eq!(split_session_dir("2026-04-10-harbour"), (date, "-harbour"));
+        assert_eq!(split_session_dir("2026-04-100"), (None, "2026-04-100"));
+        assert_eq!(split_session_dir("harbour"), (None, "harbour"));
+
+        let tmp = tempfile::TempDir::new().unwrap();
+        for (dir, pic_md) in [
+            ("2026-04-10", "# Morning\n"),
+            ("2026-04-10-Harbour Walk", "# Harbour\n"),
+            ("ferry", "+++\ndate = 2026-04-12\n+++\n# Ferry\n"),
+            ("2026-04-10-ferry", "+++\ndate = 2026-04-12\n+++\n# Moved\n"),
+            ("undated", "# Undated\n"),
+        ] {
+            fs::create_dir(tmp.path().join(dir)).unwrap();
+            fs::write(tmp.path().join(dir).join("PIC.md"), pic_md).unwrap();
+        }
+        let gallery = get_pics_gallery(tmp.path()).unwrap();
+        let sessions: Vec<(&str, &str)> = gallery
+            .sessions
+            .iter()
+            .map(|session| (session.slug.as_str(), session.title.as_str()))
+            .collect();
+        // "ferry" is skipped, its slug is taken by "2026-04-10-ferry" which sorts first.
+        assert_eq!(
+            sessions,
+            [
+                ("2026-04-12-ferry", "Moved"),
+                ("2026-04-10", "Morning"),
+                ("2026-04-10-harbour-walk", "Harbour"),
+            ]
+        );
+        assert_eq!(gallery.sessions[0].date_str, "2026-04-12");
+        assert_eq!(gallery.sessions[0].dir, tmp.path().join("2026-04-10-ferry"));
+        // Templates get the resolved date, with or without a front matter date.
+        let dates: Vec<_> = gallery
+            .sessions
+            .iter()
+            .map(|session| serde_json::to_value(session).unwrap()["date"].clone())
+            .collect();
+        assert_eq!(
+            dates,
+            [
+                json!("2026-04-12"),
+                json!("2026-04-10"),
+                json!("2026-04-10")
+            ]
+        );
+    }
+
+    #[test]
+    fn covers_must_be_shown_photos() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::write(tmp.path().join("a.jpg"), b"").unwrap();
+        fs::write(tmp.path().join("c.jpg"), b"").unwrap();
+        let (_, _, photos) = parse_pic_md(
+            "# T\n\n## A\nfile: a.jpg\n\n## B\nfile: b.jpg\n\n## C\nfile: c.jpg\nhidden: true\n",
+        );
+        assert_eq!(cover_problem("a.jpg", &photos, tmp.path()), None);
+        assert_eq!(
+            cover_problem("b.jpg", &photos, tmp.path()),
+            Some("is missing")
+        );
+        assert_eq!(
+            cover_problem("c.jpg", &photos, tmp.path()),
+            Some("is hidden")
+        );
+        assert_eq!(
+            cover_problem("d.jpg", &photos, tmp.path()),
+            Some("isn't a photo in PIC.md")
+        );
+    }
+
+    #[test]
+    fn tags_without_a_slug_are_dropped() {
+        assert_eq!(parse_tags("film, ???, night,"), ["film", "night"]);
+        let url = |tag: &str| tag_url(&tera::Value::from(tag), &HashMap::new());
+        assert_eq!(url("Old Town").unwrap(), "/pics/tags/old-town/");
+        assert!(url("???").is_err());
+
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dir = tmp.path().join("2026-04-10");
+        fs::create_dir(&dir).unwrap();
+        fs::write(
+            dir.join("PIC.md"),
+            "+++\ntags = [\"film\", \"#\"]\n+++\n# T\n",
+        )
+        .unwrap();
+        let gallery = get_pics_gallery(tmp.path()).unwrap();
+        assert_eq!(gallery.sessions[0].front_matter.tags, ["film"]);
+    }
+
     #[test]
     fn empty_input_gives_empty_results() {
         let (title, desc, photos) = parse_pic_md("");
@@ -293,63 +1417,16 @@ file: VictoriaParkBuilding.jpg
 "#;
         let (title, desc, photos) = parse_pic_md(input);
         assert_eq!(title, "First Roll");
-        assert_eq!(desc, "Testing out the new camera.");
+        assert_eq!(desc, "<p>Testing out the new camera.</p>");
         assert_eq!(photos.len(), 2);
         assert_eq!(photos[0].filename, "Botanical.jpg");
         assert_eq!(photos[0].title.as_deref(), Some("First shot of the day"));
         assert!(photos[0].maps_url.is_some());
-        assert_eq!(photos[0].description.as_deref(), Some("Beautiful light that morning."));
+        assert_eq!(
+            photos[0].description.as_deref(),
+            Some("<p>Beautiful light that morning.</p>")
+        );
         assert_eq!(photos[1].filename, "VictoriaParkBuilding.jpg");
         assert!(photos[1].maps_url.is_none());
     }
 }
-
-pub fn get_pics_gallery(pics_root: &str) -> Result<PicsGallery> {
-    let base = PathBuf::from(pics_root);
-    if !base.is_dir() {
-        return Ok(PicsGallery { sessions: vec![] });
-    }
-
-    let mut sessions = Vec::new();
-
-    for entry in fs::read_dir(&base).map_err(SiteError::from)? {
-        let entry = entry.map_err(SiteError::from)?;
-        let path = entry.path();
-        if !path.is_dir() {
-            continue;
-        }
-        let dir_name = path
-            .file_name()
-            .and_then(OsStr::to_str)
-            .unwrap_or("")
-            .to_owned();
-
-        let date = match NaiveDate::parse_from_str(&dir_name, "%Y-%m-%d") {
-            Ok(d) => d,
-            Err(_) => continue,
-        };
-
-        let pic_md_path = path.join("PIC.md");
-        if !pic_md_path.exists() {
-            eprintln!("Warning: no PIC.md found in {:?}, skipping", path);
-            continue;
-        }
-
-        let contents = fs::read_to_string(&pic_md_path).map_err(SiteError::from)?;
-        let (title, description, photos) = parse_pic_md(&contents);
-
-        println!("Successfully parsed pics session {:?}", pic_md_path);
-
-        sessions.push(PicSession {
-            date,
-            date_str: dir_name,
-            title,
-            description,
-            photos,
-        });
-    }
-
-    sessions.sort_by(|a, b| b.date.cmp(&a.date));
-
-    Ok(PicsGallery { sessions })
-}
@@ -0,0 +1,145 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use serde::Serialize;
+use std::io::Cursor;
+
+/// PREVIEW_SIZE is the largest side of the low quality preview, in pixels.
+const PREVIEW_SIZE: u32 = 16;
+
+/// THUMBNAIL_SIZE is the side of the square thumbnails, in pixels: twice the
+/// widest a thumbnail is shown, for high density screens.
+pub const THUMBNAIL_SIZE: u32 = 480;
+
+/// THUMBNAIL_QUALITY is the JPEG quality of the thumbnails.
+const THUMBNAIL_QUALITY: u8 = 80;
+
+/// Placeholder is what a page shows in place of a lazy-loaded photo:
+/// its intrinsic size, so the page doesn't shift, and a preview.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Placeholder {
+    /// width and height are after the photo's EXIF orientation is applied,
+    /// like browsers display it.
+    pub width: u32,
+    pub height: u32,
+    /// color is the average color of the photo, i.e. "#3a5f7d".
+    pub color: String,
+    /// preview is a tiny, blurry version of the photo as a `data:` URI.
+    pub preview: String,
+}
+
+/// average_color returns the mean of every pixel in `img` as a css color.
+fn average_color(img: &DynamicImage) -> String {
+    let rgb = img.to_rgb8();
+    let pixels = u64::from(rgb.width()) * u64::from(rgb.height());
+    let mut sums = [0u64; 3];
+    for pixel in rgb.pixels() {
+        for (sum, channel) in sums.iter_mut().zip(pixel.0) {
+            *sum += u64::from(channel);
+        }
+    }
+    let [r, g, b] = sums.map(|sum| sum / pixels.max(1));
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// decode_photo decodes the photo in `bytes`, turned by its EXIF orientation
+/// like browsers display it. Photos which can't be read are None.
+pub fn decode_photo(bytes: &[u8]) -> Option<DynamicImage> {
+    let mut decoder = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()?
+        .into_decoder()
+        .ok()?;
+    let orientation = decoder.orientation().ok()?;
+    let mut img = DynamicImage::from_decoder(decoder).ok()?;
+    img.apply_orientation(orientation);
+    Some(img)
+}
+
+/// make_placeholder computes the [Placeholder](crate::placeholder::Placeholder)
+/// of a photo decoded by [decode_photo](crate::placeholder::decode_photo).
+pub fn make_placeholder(img: &DynamicImage) -> Option<Placeholder> {
+    let thumbnail = img.resize(PREVIEW_SIZE, PREVIEW_SIZE, FilterType::Triangle);
+    let mut png = Cursor::new(Vec::new());
+    thumbnail
+        .to_rgb8()
+        .write_to(&mut png, ImageFormat::Png)
+        .ok()?;
+
+    Some(Placeholder {
+        width: img.width(),
+        height: img.height(),
+        color: average_color(&thumbnail),
+        preview: format!("data:image/png;base64,{}", BASE64.encode(png.get_ref())),
+    })
+}
+
+/// make_thumbnail crops a photo decoded by [decode_photo](crate::placeholder::decode_photo)
+/// to a square of [THUMBNAIL_SIZE](crate::placeholder::THUMBNAIL_SIZE) and encodes it as a JPEG.
+pub fn make_thumbnail(img: &DynamicImage) -> Option<Vec<u8>> {
+    let size = THUMBNAIL_SIZE.min(img.width()).min(img.height());
+    let thumbnail = img.resize_to_fill(size, size, FilterType::Triangle);
+    let mut jpeg = Vec::new();
+    let encoder = JpegEncoder::new_with_quality(&mut jpeg, THUMBNAIL_QUALITY);
+    thumbnail.to_rgb8().write_with_encoder(encoder).ok()?;
+    Some(jpeg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn encode(img: &RgbImage, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        img.write_to(&mut bytes, format).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn placeholder_has_size_color_and_preview() {
+        let img = RgbImage::from_fn(120, 40, |x, _| {
+            if x < 60 {
+                Rgb([200, 0, 0])
+            } else {
+                Rgb([0, 0, 200])
+            }
+        });
+
+        let img = decode_photo(&encode(&img, ImageFormat::Png)).unwrap();
+        let placeholder = make_placeholder(&img).unwrap();
+        assert_eq!((placeholder.width, placeholder.height), (120, 40));
+        assert_eq!(placeholder.color, "#640064");
+        let preview = placeholder
+            .preview
+            .strip_prefix("data:image/png;base64,")
+            .unwrap();
+        let preview = image::load_from_memory(&BASE64.decode(preview).unwrap()).unwrap();
+        assert_eq!((preview.width(), preview.height()), (16, 5));
+    }
+
+    #[test]
+    fn thumbnails_are_small_squares() {
+        let wide = RgbImage::from_pixel(1600, 900, Rgb([0, 120, 0]));
+        let thumbnail = make_thumbnail(&DynamicImage::ImageRgb8(wide)).unwrap();
+        let thumbnail = image::load_from_memory_with_format(&thumbnail, ImageFormat::Jpeg).unwrap();
+        assert_eq!(
+            (thumbnail.width(), thumbnail.height()),
+            (THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        );
+
+        // Small photos aren't scaled up.
+        let small = DynamicImage::ImageRgb8(RgbImage::new(32, 64));
+        let thumbnail = image::load_from_memory(&make_thumbnail(&small).unwrap()).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (32, 32));
+    }
+
+    #[test]
+    fn unreadable_photos_cannot_be_decoded() {
+        assert!(decode_photo(b"not a jpeg").is_none());
+        let jpeg = encode(&RgbImage::new(8, 8), ImageFormat::Jpeg);
+        assert!(decode_photo(&jpeg[..jpeg.len() / 2]).is_none());
+    }
+}
@@ -0,0 +1,261 @@
+use crate::error::SiteError;
+use crate::output::OutputSink;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use miette::Result;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Files smaller than this aren't worth compressing.
+pub const MIN_SIZE: u64 = 1024;
+
+/// Extensions of the files which get `.gz` and `.br` siblings.
+const COMPRESSIBLE_EXTENSIONS: &[&str] = &["html", "xml", "css", "js", "json", "svg"];
+
+fn gzip(contents: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(contents).map_err(SiteError::from)?;
+    Ok(encoder.finish().map_err(SiteError::from)?)
+}
+
+fn brotli(contents: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    {
+        let mut writer = brotli::CompressorWriter::new(&mut out, 4096, 11, 22);
+        writer.write_all(contents).map_err(SiteError::from)?;
+    }
+    Ok(out)
+}
+
+fn is_compressible(path: &Path) -> bool {
+    let ext = path.extension().and_then(OsStr::to_str).unwrap_or("");
+    COMPRESSIBLE_EXTENSIONS.contains(&ext)
+}
+
+/// with_suffix appends `suffix` to the file name, i.e. index.html -> index.html.gz
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// is_cache_entry tells whether `path` is named like a file the cache writes:
+/// `<sha256>.gz` or `<sha256>.br`, possibly with a `.partial` suffix.
+fn is_cache_entry(path: &Path) -> bool {
+    let name = path.file_name().and_then(OsStr::to_str).unwrap_or("");
+    let name = name.strip_suffix(".partial").unwrap_or(name);
+    let Some((hash, ext)) = name.split_once('.') else {
+        return false;
+    };
+    hash.len() == 64
+        && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        && matches!(ext, "gz" | "br")
+}
+
+/// PrecompressSink wraps another sink, writing `.gz` and `.br` siblings next
+/// to every compressible file of at least `MIN_SIZE` bytes.
+///
+/// Compressed files are cached in `cache_dir` by content hash, so files which
+/// haven't changed since the last build are reused rather than recompressed.
+/// Cache entries not used by the build, and any left partially written by an
+/// interrupted build, are removed when it finishes. Other files in `cache_dir`
+/// are left alone.
+pub struct PrecompressSink<'a> {
+    inner: &'a mut dyn OutputSink,
+    cache_dir: PathBuf,
+    used: HashSet<PathBuf>,
+    compressed: usize,
+    cached: usize,
+}
+
+impl<'a> PrecompressSink<'a> {
+    pub fn new(inner: &'a mut dyn OutputSink, cache_dir: impl Into<PathBuf>) -> Self {
+        PrecompressSink {
+            inner,
+            cache_dir: cache_dir.into(),
+            used: HashSet::new(),
+            compressed: 0,
+            cached: 0,
+        }
+    }
+}
+
+impl OutputSink for PrecompressSink<'_> {
+    fn begin(&mut self) -> Result<()> {
+        fs::create_dir_all(&self.cache_dir).map_err(SiteError::from)?;
+        self.inner.begin()
+    }
+
+    fn write(&mut self, path: &Path, contents: &[u8]) -> Result<()> {
+        self.inner.write(path, contents)?;
+        if !is_compressible(path) || (contents.len() as u64) < MIN_SIZE {
+            return Ok(());
+        }
+        let hash: String = Sha256::digest(contents)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+
+        for (suffix, compress) in [
+            (".gz", gzip as fn(&[u8]) -> Result<Vec<u8>>),
+            (".br", brotli),
+        ] {
+            let cache_path = self.cache_dir.join(format!("{}{}", hash, suffix));
+            let compressed = match fs::read(&cache_path) {
+                Ok(cached) => {
+                    self.cached += 1;
+                    cached
+                }
+                Err(_) => {
+                    let compressed = compress(contents)?;
+                    // Cache entries are trusted by name, so an interrupted build must
+                    // never leave a partial one: write it aside and rename it into place.
+                    let partial = with_suffix(&cache_path, ".partial");
+                    fs::write(&partial, &compressed).map_err(SiteError::from)?;
+                    fs::rename(&partial, &cache_path).map_err(SiteError::from)?;
+                    self.compressed += 1;
+                    compressed
+                }
+            };
+            self.inner.write(&with_suffix(path, suffix), &compressed)?;
+            self.used.insert(cache_path);
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        for entry in fs::read_dir(&self.cache_dir).map_err(SiteError::from)? {
+            let stale = entry.map_err(SiteError::from)?.path();
+            if is_cache_entry(&stale) && stale.is_file() && !self.used.contains(&stale) {
+                fs::remove_file(stale).map_err(SiteError::from)?;
+            }
+        }
+        println!(
+            "Wrote {} compressed files ({} reused from cache)",
+            self.compressed + self.cached,
+            self.cached
+        );
+        self.inner.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::walk_files;
+    use crate::output::MemorySink;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    fn build(sink: &mut MemorySink, cache_dir: &Path, files: &[(&str, &[u8])]) {
+        let mut precompressed = PrecompressSink::new(sink, cache_dir);
+        precompressed.begin().unwrap();
+        for (path, contents) in files {
+            precompressed.write(Path::new(path), contents).unwrap();
+        }
+        precompressed.finish().unwrap();
+    }
+
+    #[test]
+    fn writes_and_reuses_compressed_siblings() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let cache_dir = tmp.path().join("cache");
+        let page = "<p>hello</p>".repeat(200);
+        let files: [(&str, &[u8]); 3] = [
+            ("blog/index.html", page.as_bytes()),
+            ("small.css", b"a{}"),
+            ("card.png", &[0; 4096]),
+        ];
+        let mut sink = MemorySink::new();
+        build(&mut sink, &cache_dir, &files);
+
+        let mut unzipped = String::new();
+        GzDecoder::new(sink.get("blog/index.html.gz").unwrap())
+            .read_to_string(&mut unzipped)
+            .unwrap();
+        assert_eq!(unzipped, page);
+        let mut unbrotlied = String::new();
+        brotli::Decompressor::new(sink.get("blog/index.html.br").unwrap(), 4096)
+            .read_to_string(&mut unbrotlied)
+            .unwrap();
+        assert_eq!(unbrotlied, page);
+        assert_eq!(sink.get("blog/index.html"), Some(page.as_bytes()));
+        assert!(sink.get("small.css.gz").is_none());
+        assert!(sink.get("card.png.gz").is_none());
+
+        // Unchanged files are copied from the cache rather than recompressed.
+        for entry in walk_files(&cache_dir).unwrap() {
+            fs::write(entry, "from cache").unwrap();
+        }
+        build(&mut sink, &cache_dir, &files);
+        assert_eq!(sink.get("blog/index.html.gz"), Some(&b"from cache"[..]));
+
+        // A cache entry which no longer matches any output file is pruned.
+        let changed = "changed ".repeat(200);
+        let old_entries = walk_files(&cache_dir).unwrap();
+        build(
+            &mut sink,
+            &cache_dir,
+            &[("blog/index.html", changed.as_bytes())],
+        );
+        let new_entries = walk_files(&cache_dir).unwrap();
+        assert_eq!(new_entries.len(), 2);
+        assert!(old_entries.iter().all(|entry| !new_entries.contains(entry)));
+    }
+
+    #[test]
+    fn interrupted_writes_are_never_reused() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let cache_dir = tmp.path().join("cache");
+        let page = "<p>hello</p>".repeat(200);
+        let files: [(&str, &[u8]); 1] = [("index.html", page.as_bytes())];
+        let mut sink = MemorySink::new();
+        build(&mut sink, &cache_dir, &files);
+
+        // A build interrupted mid-write leaves only a partial file, which isn't an entry.
+        let entries = walk_files(&cache_dir).unwrap();
+        for entry in &entries {
+            fs::rename(entry, with_suffix(entry, ".partial")).unwrap();
+            fs::write(with_suffix(entry, ".partial"), "trunc").unwrap();
+        }
+        let mut sink = MemorySink::new();
+        build(&mut sink, &cache_dir, &files);
+        let mut unzipped = String::new();
+        GzDecoder::new(sink.get("index.html.gz").unwrap())
+            .read_to_string(&mut unzipped)
+            .unwrap();
+        assert_eq!(unzipped, page);
+        let mut left = walk_files(&cache_dir).unwrap();
+        left.sort();
+        let mut expected = entries;
+        expected.sort();
+        assert_eq!(left, expected);
+    }
+
+    #[test]
+    fn only_cache_entries_are_pruned() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let cache_dir = tmp.path();
+        let stale = format!("{}.gz", "0".repeat(64));
+        let foreign = ["notes.txt", "site.toml", "abc.gz", "nested/index.html"];
+        fs::create_dir(cache_dir.join("nested")).unwrap();
+        for name in foreign.iter().chain([&stale.as_str()]) {
+            fs::write(cache_dir.join(name), "mine").unwrap();
+        }
+
+        let page = "<p>hello</p>".repeat(200);
+        build(
+            &mut MemorySink::new(),
+            cache_dir,
+            &[("index.html", page.as_bytes())],
+        );
+        for name in foreign {
+            assert!(cache_dir.join(name).is_file(), "{} should survive", name);
+        }
+        assert!(!cache_dir.join(&stale).exists());
+    }
+}
@@ -1,140 +1,215 @@
 use crate::blog::OrgBlog;
-use crate::context::get_base_context;
+use crate::camera::strip_location;
+use crate::cards::{CARD_FILENAME, render_article_card, render_session_card};
+use crate::config::SiteConfig;
+use crate::context::{PageMeta, PhotoNav, SiteContext, get_base_context};
 use crate::error::SiteError;
-use crate::pics::PicsGallery;
+use crate::minify::minify_html;
+use crate::output::OutputSink;
+use crate::pages::ContentPage;
+use crate::pics::{MAP_GEOJSON, MAP_URI, PicsGallery, TAGS_URI, THUMBNAILS_DIR};
+use crate::template_vars::check_template_vars;
 use miette::Result;
+use std::collections::{BTreeSet, HashSet};
 use std::ffi::OsStr;
-use std::fs::{self, File};
-use std::io::Write;
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
 use tera::{Context, Tera};
 
-pub fn generate_site(tera: &Tera, output_dir: &str, blog: &OrgBlog, pics: &PicsGallery) -> Result<()> {
-    // Helper function to render and write a file
-    let render_and_write =
-        |template_name: &str, context: &Context, output_path: &str| -> Result<()> {
-            println!("Rendering {} to {}", template_name, output_path);
-            let content = tera
-                .render(template_name, context)
-                .map_err(SiteError::from)?;
-            let path = Path::new(output_dir).join(output_path);
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent).map_err(SiteError::from)?;
-            }
-            let mut file = File::create(path).map_err(SiteError::from)?;
-            file.write_all(content.as_bytes())
-                .map_err(SiteError::from)?;
-            Ok(())
+/// Renderer renders templates and writes the result to the output sink.
+struct Renderer<'a> {
+    tera: &'a Tera,
+    sink: &'a mut dyn OutputSink,
+    minify: bool,
+    /// written is every path written so far, so no output silently replaces another.
+    written: HashSet<PathBuf>,
+}
+
+impl Renderer<'_> {
+    /// write writes `contents` to `path`, failing if something was already written there.
+    fn write(&mut self, path: &Path, contents: &[u8]) -> Result<()> {
+        if !self.written.insert(path.to_path_buf()) {
+            return Err(SiteError::DuplicatePage(path.display().to_string()).into());
+        }
+        self.sink.write(path, contents)
+    }
+
+    /// render checks that `context` provides every variable the template uses,
+    /// then renders it to `output_path`.
+    fn render(
+        &mut self,
+        template_name: &str,
+        context: &SiteContext,
+        output_path: &str,
+    ) -> Result<()> {
+        println!("Rendering {} to {}", template_name, output_path);
+        let context = serde_json::to_value(context)
+            .map_err(tera::Error::from)
+            .map_err(SiteError::from)?;
+        check_template_vars(self.tera, template_name, &context)?;
+        let context = Context::from_value(context).map_err(SiteError::from)?;
+        let content = self
+            .tera
+            .render(template_name, &context)
+            .map_err(SiteError::from)?;
+        let content = if self.minify && output_path.ends_with(".html") {
+            minify_html(&content)
+        } else {
+            content.into_bytes()
         };
+        self.write(Path::new(output_path), &content)
+    }
+}
 
-    // Generate index page
-    let mut context = get_base_context("/", blog);
-    context.kv.insert("title".to_owned(), "home".into());
-    render_and_write("index.html.tera", &(&context).into(), "index.html")?;
-
-    // Generate resume page
-    let mut context = get_base_context("/resume", blog);
-    context.kv.insert("title".to_owned(), "resume".into());
-    render_and_write("resume.html.tera", &(&context).into(), "resume/index.html")?;
-
-    // Generate blog index page
-    let mut context = get_base_context("/blog", blog);
-    context.kv.insert("title".to_owned(), "blog".into());
-    render_and_write(
-        "blog/blog_root.html.tera",
-        &(&context).into(),
-        "blog/index.html",
-    )?;
+pub fn generate_site(
+    tera: &Tera,
+    sink: &mut dyn OutputSink,
+    config: &SiteConfig,
+    blog: &OrgBlog,
+    pages: &[ContentPage],
+    pics: &PicsGallery,
+    minify: bool,
+) -> Result<()> {
+    let mut out = Renderer {
+        tera,
+        sink,
+        minify,
+        written: HashSet::new(),
+    };
 
-    // Generate linkedin page
-    let mut context = get_base_context("/linkedin", blog);
-    context.kv.insert("title".to_owned(), "linkedin".into());
-    render_and_write(
-        "linkedin.html.tera",
-        &(&context).into(),
-        "linkedin/index.html",
-    )?;
+    // Generate configured pages
+    for page in &config.pages {
+        let mut context = get_base_context(&page.uri, blog, config);
+        if let Some(title) = &page.title {
+            context.set_title(title);
+        }
+        // Pages written somewhere else, like 404.html, aren't served from their uri.
+        if page.output.is_some() {
+            context.meta.canonical_url = None;
+        }
+        out.render(&page.template, &context, &page.output_path())?;
+    }
 
-    // Generate github page
-    let mut context = get_base_context("/github", blog);
-    context.kv.insert("title".to_owned(), "github".into());
-    render_and_write("github.html.tera", &(&context).into(), "github/index.html")?;
+    // Generate content pages
+    for page in pages {
+        let output_path = format!("{}/index.html", page.slug);
+        let mut context = get_base_context(&format!("/{}", page.slug), blog, config);
+        context.set_title(&page.title);
+        context.meta = PageMeta::content_page(page);
+        context.content = Some(page);
+        out.render("page.html.tera", &context, &output_path)?;
+    }
 
     // Generate robots.txt
     println!("Generating robots.txt");
-    let mut file =
-        File::create(Path::new(output_dir).join("robots.txt")).map_err(SiteError::from)?;
-    file.write_all(b"User-agent: *\nDisallow:")
-        .map_err(SiteError::from)?;
-
-    // Generate RSS feed
-    let rss_context = get_base_context("/blog", blog);
-    render_and_write(
-        "blog-rss.xml.tera",
-        &(&rss_context).into(),
-        "feed/index.xml",
-    )?;
-
-    // Generate 404 page
-    let mut context = get_base_context("/", blog);
-    context.kv.insert("title".to_owned(), "404".into());
-    context.kv.insert("blog_uri".to_owned(), "".into());
-    render_and_write("404.html.tera", &(&context).into(), "404.html")?;
-
-    // Generate 500 page
-    let mut context = get_base_context("/", blog);
-    context.kv.insert("title".to_owned(), "500".into());
-    context.kv.insert("uri".to_owned(), "/".into());
-    render_and_write("500.html.tera", &(&context).into(), "500.html")?;
+    out.write(Path::new("robots.txt"), b"User-agent: *\nDisallow:")?;
 
     // Generate blog articles
     for (slug, blog_post) in &blog.html {
-        let mut context = get_base_context("/blog", blog);
-        context.kv.insert("title".to_owned(), "blog".to_owned());
-        context.curr_blog = Some(blog_post);
-        context.kv.insert("curr_slug".to_owned(), slug.clone());
+        let mut context = get_base_context("/blog", blog, config);
+        context.set_title("blog");
+        context.meta = PageMeta::article(blog_post);
+        context.article = Some(blog_post);
         let output_path = format!("blog/{}/index.html", slug);
-        render_and_write(
-            "blog/blog_article.html.tera",
-            &(&context).into(),
-            &output_path,
-        )?;
+        out.render("blog/blog_article.html.tera", &context, &output_path)?;
+
+        let card = render_article_card(blog_post, &context.base["domain_name"])?;
+        out.write(&Path::new("blog").join(slug).join(CARD_FILENAME), &card)?;
     }
 
-    // Copy pics images and generate pics page
+    // Copy the photos of each session, without their location, and write their
+    // thumbnails. Hidden photos and anything else in the session directory stay
+    // unpublished.
     for session in &pics.sessions {
-        let src_dir = Path::new("pics").join(&session.date_str);
-        let dest_dir = Path::new(output_dir).join("pics").join(&session.date_str);
-        if src_dir.is_dir() {
-            fs::create_dir_all(&dest_dir).map_err(SiteError::from)?;
-            for entry in fs::read_dir(&src_dir).map_err(SiteError::from)? {
-                let entry = entry.map_err(SiteError::from)?;
-                let path = entry.path();
-                if path.is_file() {
-                    let ext = path.extension().and_then(OsStr::to_str).unwrap_or("");
-                    if ext != "md" {
-                        let dest = dest_dir.join(path.file_name().unwrap());
-                        fs::copy(&path, &dest).map_err(SiteError::from)?;
-                    }
-                }
+        let dest_dir = Path::new("pics").join(&session.slug);
+        let filenames: BTreeSet<&str> = session
+            .photos
+            .iter()
+            .filter(|photo| !photo.hidden)
+            .map(|photo| photo.filename.as_str())
+            // Only files directly in the session directory are published.
+            .filter(|filename| Path::new(filename).file_name() == Some(OsStr::new(filename)))
+            .collect();
+        for filename in filenames {
+            let path = session.dir.join(filename);
+            if !path.is_file() {
+                continue;
+            }
+            if filename == CARD_FILENAME || filename == THUMBNAILS_DIR {
+                return Err(SiteError::ReservedFileName(path).into());
             }
+            let contents = fs::read(&path).map_err(SiteError::from)?;
+            out.write(&dest_dir.join(filename), &strip_location(contents))?;
+        }
+        for thumbnail in session
+            .shown_photos()
+            .filter_map(|photo| photo.thumbnail.as_ref())
+        {
+            out.write(&dest_dir.join(&thumbnail.path), &thumbnail.jpeg)?;
         }
     }
 
-    let mut context = get_base_context("/pics", blog);
-    context.kv.insert("title".to_owned(), "pics".into());
-    let mut pics_context: Context = (&context).into();
-    pics_context.insert("pics", pics);
-    render_and_write("pics.html.tera", &pics_context, "pics/index.html")?;
+    // Generate the pics index pages and the archive of each year
+    for index in pics.index_pages().iter().chain(&pics.year_pages()) {
+        let mut context = get_base_context(&index.uri, blog, config);
+        context.set_title(&index.title);
+        context.pics_index = Some(index);
+        let output_path = format!("{}/index.html", index.uri.trim_start_matches('/'));
+        out.render("pics.html.tera", &context, &output_path)?;
+    }
 
     // Generate individual session pages
     for session in &pics.sessions {
-        let mut context = get_base_context("/pics", blog);
-        context.kv.insert("title".to_owned(), session.title.clone());
-        let mut session_context: Context = (&context).into();
-        session_context.insert("session", session);
-        let output_path = format!("pics/{}/index.html", session.date_str);
-        render_and_write("pics/pic_session.html.tera", &session_context, &output_path)?;
+        let mut context = get_base_context("/pics", blog, config);
+        context.set_title(&session.title);
+        context.meta = PageMeta::pic_session(session);
+        context.session = Some(session);
+        let output_path = format!("pics/{}/index.html", session.slug);
+        out.render("pics/pic_session.html.tera", &context, &output_path)?;
+
+        for (photo, prev, next) in session.photo_pages() {
+            let mut context = get_base_context("/pics", blog, config);
+            context.meta = PageMeta::pic_photo(session, photo);
+            context.page.title = context.meta.title.clone();
+            context.session = Some(session);
+            context.photo = Some(photo);
+            context.photo_nav = Some(PhotoNav { prev, next });
+            let output_path = format!("pics/{}/{}/index.html", session.slug, photo.slug);
+            out.render("pics/pic_photo.html.tera", &context, &output_path)?;
+        }
+
+        let card = render_session_card(session, &context.base["domain_name"])?;
+        let card_path = Path::new("pics").join(&session.slug).join(CARD_FILENAME);
+        out.write(&card_path, &card)?;
+    }
+
+    // Generate the map of geotagged photos
+    let geojson = pics.geojson().to_string();
+    let map_dir = MAP_URI.trim_start_matches('/');
+    out.write(&Path::new(map_dir).join(MAP_GEOJSON), geojson.as_bytes())?;
+    let mut context = get_base_context(MAP_URI, blog, config);
+    context.set_title("pics map");
+    out.render(
+        "pics/map.html.tera",
+        &context,
+        &format!("{}/index.html", map_dir),
+    )?;
+
+    // Generate an album per tag, and their index
+    let albums = pics.albums();
+    let mut context = get_base_context(TAGS_URI, blog, config);
+    context.set_title("pics tags");
+    context.albums = Some(&albums);
+    out.render("pics/tags.html.tera", &context, "pics/tags/index.html")?;
+
+    for album in &albums {
+        let path = format!("{}/{}", TAGS_URI, album.slug);
+        let mut context = get_base_context(&path, blog, config);
+        context.meta = PageMeta::album(album);
+        context.page.title = context.meta.title.clone();
+        context.album = Some(album);
+        let output_path = format!("pics/tags/{}/index.html", album.slug);
+        out.render("pics/album.html.tera", &context, &output_path)?;
     }
 
     Ok(())
@@ -0,0 +1,309 @@
+use crate::error::SiteError;
+use miette::Result;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use tera::Tera;
+use tera::ast::{Expr, ExprVal, FunctionCall, Node};
+
+/// Variables Tera provides itself, rather than the context.
+const BUILTIN_VARIABLES: &[&str] = &["loop", "__tera_context"];
+
+/// Checker collects the variables a template uses which aren't in its context.
+struct Checker<'a> {
+    tera: &'a Tera,
+    context: &'a Value,
+    /// locals are the variables defined by the template itself, i.e. by
+    /// `{% set %}` or `{% for %}`, innermost last. Their value is known when
+    /// they come from the context, i.e. the first item of the array a loop is over.
+    locals: Vec<(String, Option<&'a Value>)>,
+    missing: BTreeSet<String>,
+}
+
+/// root_and_path splits a Tera identifier like `blog.blog_files[0].title`
+/// into its root (`blog`) and the object keys which can be checked (`blog_files`).
+fn root_and_path(ident: &str) -> (&str, Vec<&str>) {
+    let dotted = ident.split('[').next().unwrap_or_default();
+    let mut parts = dotted.split('.');
+    let root = parts.next().unwrap_or_default();
+    (root, parts.collect())
+}
+
+impl<'a> Checker<'a> {
+    /// resolve returns the value of `ident`, if it's known, recording it as
+    /// missing if it isn't in the context.
+    fn resolve(&mut self, ident: &str) -> Option<&'a Value> {
+        let (root, path) = root_and_path(ident);
+        let mut value = if BUILTIN_VARIABLES.contains(&root) {
+            return None;
+        } else if let Some((_, local)) = self.locals.iter().rev().find(|(name, _)| name == root) {
+            (*local)?
+        } else if let Some(value) = self.context.get(root) {
+            value
+        } else {
+            self.missing.insert(root.to_owned());
+            return None;
+        };
+        for (i, key) in path.iter().enumerate() {
+            // Only objects can be checked: arrays are indexed and null is an absent
+            // optional, which the template is expected to test for.
+            let Value::Object(object) = value else {
+                return None;
+            };
+            match object.get(*key) {
+                Some(child) => value = child,
+                None => {
+                    self.missing
+                        .insert(format!("{}.{}", root, path[..=i].join(".")));
+                    return None;
+                }
+            }
+        }
+        Some(value)
+    }
+
+    fn check_ident(&mut self, ident: &str) {
+        self.resolve(ident);
+    }
+
+    /// check_value checks `expr` and returns its value, if it's known.
+    fn check_value(&mut self, expr: &Expr) -> Option<&'a Value> {
+        match &expr.val {
+            ExprVal::Ident(ident) if expr.filters.is_empty() => self.resolve(ident),
+            _ => {
+                self.check_expr(expr);
+                None
+            }
+        }
+    }
+
+    fn check_call(&mut self, call: &FunctionCall) {
+        for arg in call.args.values() {
+            self.check_expr(arg);
+        }
+    }
+
+    fn check_expr(&mut self, expr: &Expr) {
+        // `x | default(value=...)` is for variables which may not exist.
+        let has_default = expr.filters.first().is_some_and(|f| f.name == "default");
+        match &expr.val {
+            ExprVal::Ident(ident) if !has_default => self.check_ident(ident),
+            ExprVal::Math(math) => {
+                self.check_expr(&math.lhs);
+                self.check_expr(&math.rhs);
+            }
+            ExprVal::Logic(logic) => {
+                self.check_expr(&logic.lhs);
+                self.check_expr(&logic.rhs);
+            }
+            ExprVal::Test(test) => {
+                // `x is defined` is for variables which may not exist.
+                if test.name != "defined" && test.name != "undefined" {
+                    self.check_ident(&test.ident);
+                }
+                for arg in &test.args {
+                    self.check_expr(arg);
+                }
+            }
+            ExprVal::MacroCall(call) => {
+                for arg in call.args.values() {
+                    self.check_expr(arg);
+                }
+            }
+            ExprVal::FunctionCall(call) => self.check_call(call),
+            ExprVal::Array(values) => {
+                for value in values {
+                    self.check_expr(value);
+                }
+            }
+            ExprVal::StringConcat(concat) => {
+                for value in &concat.values {
+                    if let ExprVal::Ident(ident) = value {
+                        self.check_ident(ident);
+                    }
+                }
+            }
+            ExprVal::In(is_in) => {
+                self.check_expr(&is_in.lhs);
+                self.check_expr(&is_in.rhs);
+            }
+            _ => {}
+        }
+        for filter in &expr.filters {
+            self.check_call(filter);
+        }
+    }
+
+    /// check_block checks the most specific definition of `name` in `chain`,
+    /// and the definitions it pulls in with `super()`.
+    fn check_block(&mut self, name: &str, chain: &[&tera::Template]) {
+        let definitions: Vec<_> = chain
+            .iter()
+            .filter_map(|template| template.blocks.get(name))
+            .collect();
+        for definition in definitions {
+            self.check_nodes(&definition.body, chain);
+            if !definition
+                .body
+                .iter()
+                .any(|node| matches!(node, Node::Super))
+            {
+                break;
+            }
+        }
+    }
+
+    fn check_nodes(&mut self, nodes: &[Node], chain: &[&tera::Template]) {
+        for node in nodes {
+            match node {
+                Node::VariableBlock(_, expr) => self.check_expr(expr),
+                Node::Set(_, set) => {
+                    let value = self.check_value(&set.value);
+                    self.locals.push((set.key.clone(), value));
+                }
+                Node::Include(_, names, ignore_missing) => {
+                    for name in names {
+                        match self.tera.get_template(name) {
+                            Ok(template) => self.check_nodes(&template.ast, &[template]),
+                            Err(_) if *ignore_missing => {}
+                            Err(_) => {
+                                self.missing.insert(format!("include \"{}\"", name));
+                            }
+                        }
+                    }
+                }
+                Node::FilterSection(_, section, _) => {
+                    self.check_call(&section.filter);
+                    self.check_nodes(&section.body, chain);
+                }
+                Node::Block(_, block, _) => self.check_block(&block.name, chain),
+                Node::Forloop(_, forloop, _) => {
+                    // Items are all the same type, so the first one stands in for the rest.
+                    let item = match self.check_value(&forloop.container) {
+                        Some(Value::Array(items)) if forloop.key.is_none() => items.first(),
+                        Some(Value::Object(items)) => items.values().next(),
+                        _ => None,
+                    };
+                    let scope = self.locals.len();
+                    if let Some(key) = &forloop.key {
+                        self.locals.push((key.clone(), None));
+                    }
+                    self.locals.push((forloop.value.clone(), item));
+                    self.check_nodes(&forloop.body, chain);
+                    self.locals.truncate(scope);
+                    if let Some(empty_body) = &forloop.empty_body {
+                        self.check_nodes(empty_body, chain);
+                    }
+                }
+                Node::If(conditions, _) => {
+                    for (_, condition, body) in &conditions.conditions {
+                        self.check_expr(condition);
+                        self.check_nodes(body, chain);
+                    }
+                    if let Some((_, body)) = &conditions.otherwise {
+                        self.check_nodes(body, chain);
+                    }
+                }
+                // Macros only see their arguments, not the page context.
+                _ => {}
+            }
+        }
+    }
+}
+
+/// check_template_vars checks that every variable `template_name` uses, in every
+/// branch, block and included template, is provided by `context`. Tera only
+/// catches the ones it happens to render, so a typo in an `{% if %}` would
+/// otherwise render as if the condition was false.
+pub fn check_template_vars(tera: &Tera, template_name: &str, context: &Value) -> Result<()> {
+    let template = tera.get_template(template_name).map_err(SiteError::from)?;
+    let mut chain = vec![template];
+    for parent in &template.parents {
+        chain.push(tera.get_template(parent).map_err(SiteError::from)?);
+    }
+    let root = chain[chain.len() - 1];
+
+    let mut checker = Checker {
+        tera,
+        context,
+        locals: Vec::new(),
+        missing: BTreeSet::new(),
+    };
+    checker.check_nodes(&root.ast, &chain);
+
+    if checker.missing.is_empty() {
+        return Ok(());
+    }
+    let missing: Vec<String> = checker.missing.into_iter().collect();
+    Err(SiteError::UndefinedVariables(template_name.to_owned(), missing.join(", ")).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn check(templates: &[(&str, &str)], context: Value) -> std::result::Result<(), String> {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(templates.to_vec()).unwrap();
+        check_template_vars(&tera, templates[templates.len() - 1].0, &context)
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    #[test]
+    fn finds_typos_in_untaken_branches() {
+        let context = json!({ "page": { "title": "home" }, "article": null });
+        assert!(
+            check(
+                &[(
+                    "t",
+                    "{{ page.title }}{% if article %}{{ article.title }}{% endif %}"
+                )],
+                context.clone()
+            )
+            .is_ok()
+        );
+        let err = check(
+            &[("t", "{% if false %}{{ page.titel }}{{ artcle }}{% endif %}")],
+            context,
+        )
+        .unwrap_err();
+        assert!(err.contains("artcle, page.titel"), "{}", err);
+    }
+
+    #[test]
+    fn tracks_loop_and_set_scopes() {
+        let context = json!({ "blog": { "blog_files": [{ "title": "a" }] } });
+        let shadowed = "{% for blog in blog.blog_files %}{{ blog.title }}{{ loop.index }}{% endfor %}\
+                        {% set first = blog.blog_files | first %}{{ first.title }}";
+        assert!(check(&[("t", shadowed)], context.clone()).is_ok());
+        let leaked = "{% for post in blog.blog_files %}{% endfor %}{{ post.title }}";
+        assert!(check(&[("t", leaked)], context.clone()).is_err());
+        let typo = "{% for post in blog.blog_files %}{{ post.titel }}{% endfor %}";
+        assert!(check(&[("t", typo)], context).is_err());
+    }
+
+    #[test]
+    fn checks_blocks_and_includes_of_the_inheritance_chain() {
+        let templates = [
+            ("nav", "{{ menu }}"),
+            (
+                "base",
+                "{% include \"nav\" %}{% block title %}{{ overridden }}{% endblock title %}{% block body %}{% endblock body %}",
+            ),
+            (
+                "child",
+                "{% extends \"base\" %}{% block title %}{{ page.title }}{% endblock title %}{% block body %}{{ body }}{% endblock body %}",
+            ),
+        ];
+        let err = check(&templates, json!({ "page": { "title": "t" }, "body": "" })).unwrap_err();
+        assert!(err.contains(": menu"), "{}", err);
+        assert!(!err.contains("overridden"), "{}", err);
+        assert!(
+            check(
+                &templates,
+                json!({ "page": { "title": "t" }, "body": "", "menu": [] })
+            )
+            .is_ok()
+        );
+    }
+}
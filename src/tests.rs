@@ -1,198 +1,777 @@
-#[cfg(test)]
-mod test {
-    use rocket::http::Status;
-    use rocket::local::Client;
-
-    fn get_client() -> Client {
-        use crate::server::start_server;
-        Client::new(start_server()).expect("Failed to start_server!")
-    }
-
-    #[test]
-    fn static_pages_should_200() {
-        let routes = vec![
-            "/",
-            "/github",
-            "/linkedin",
-            "/resume",
-            "/blog",
-            "/resume_pdf",
-            "/rss",
-            "/feed",
-            "/static/favicons/apple-touch-icon.png",
-            "/static/favicons/favicon-32x32.png",
-            "/static/favicons/favicon-16x16.png",
-            "/static/favicons/site.webmanifest",
-            "/static/favicons/safari-pinned-tab.svg",
-            "/static/favicons/favicon.ico",
-            "/static/favicons/browserconfig.xml",
-            "/static/css/site.css",
-            "/static/css/blog.css",
-            "/static/css/solarized-dark.css",
-            "/static/css/bootstrap-slate.css",
-        ];
-        let client = get_client();
-        for route in routes {
-            let resp = client.get(route).dispatch();
-            assert_eq!(resp.status(), Status::Ok);
-        }
+//! Build-level tests: run the generator against the fixtures in `tests/`
+//! and check the files it produces.
+
+use crate::SiteBuilder;
+use crate::assets::walk_files;
+use crate::output::{DirSink, MemorySink, TarGzSink};
+use image::{Rgb, RgbImage};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use tera::Tera;
+
+static GOOD_BLOG_FILES: &str = "tests/good-blog-files";
+static BAD_ORG_MODE_FILES: &str = "tests/bad-org-mode-files";
+static GOOD_PICS_FILES: &str = "tests/good-pics-files";
+static GOOD_PAGES_FILES: &str = "tests/good-pages-files";
+
+/// TestSite is a site generated from the test fixtures into a temp dir.
+struct TestSite {
+    tmp: TempDir,
+}
+
+impl TestSite {
+    /// build generates a site from a copy of the fixtures, with the bad
+    /// org-mode files mixed into the blog and photos added to the pics.
+    fn build() -> Self {
+        Self::build_with(|builder| builder)
     }
 
-    #[test]
-    fn unknown_pages_should_404() {
-        let routes = vec!["/adawdawd", "/ada2wdawd/adawdw", "/ada3dawd/afwf/afwafa"];
-        let client = get_client();
-        for route in routes {
-            let resp = client.get(route).dispatch();
-            println!("{}", route);
-            assert_eq!(resp.status(), Status::NotFound);
-        }
+    fn build_with(configure: impl FnOnce(SiteBuilder) -> SiteBuilder) -> Self {
+        let tmp = TempDir::new().unwrap();
+        let blog_root = tmp.path().join("blog");
+        copy_dir(Path::new(GOOD_BLOG_FILES), &blog_root);
+        copy_dir(Path::new(BAD_ORG_MODE_FILES), &blog_root.join("2420-04-21"));
+        let pages_root = tmp.path().join("pages");
+        copy_dir(Path::new(GOOD_PAGES_FILES), &pages_root);
+        let pics_root = tmp.path().join("pics");
+        copy_dir(Path::new(GOOD_PICS_FILES), &pics_root);
+        RgbImage::from_pixel(32, 32, Rgb([255, 0, 0]))
+            .save(pics_root.join("2420-04-20/red.jpg"))
+            .unwrap();
+        RgbImage::from_pixel(32, 32, Rgb([0, 0, 255]))
+            .save(pics_root.join("2420-04-20/blue.jpg"))
+            .unwrap();
+
+        let mut sink = DirSink::new(tmp.path().join("public"));
+        let builder = SiteBuilder::new(&mut sink)
+            .blog_root(blog_root)
+            .pages_root(pages_root)
+            .pics_root(pics_root)
+            .extra_path("resume/dpbriggs_resume.pdf");
+        configure(builder).build().expect("site should build");
+        TestSite { tmp }
     }
 
-    #[test]
-    fn five_hundred_page_should_500() {
-        let client = get_client();
-        let resp = client.get("/500").dispatch();
-        assert_eq!(resp.status(), Status::InternalServerError);
-    }
-
-    #[test]
-    fn base_context_should_contain_necessary_keys() {
-        use crate::context::get_base_context;
-        let base_context = get_base_context("/").base;
-        let necessary_keys = vec![
-            "domain_name",
-            "nav_site_href",
-            "root_uri",
-            "blog_uri",
-            "rss_uri",
-            "resume_uri",
-            "linkedin_uri",
-            "github_uri",
-            "resume_uri",
-            "resume_pdf_uri",
-            "crash_uri",
-            "web_sep",
-            "admin_email",
-            "full_name",
-            "internet_handle",
-            "my_email",
-            "github_url",
-            "github_repo_url",
-            "linkedin_url",
-        ];
-        for key in necessary_keys {
-            assert!(base_context.contains_key(key))
+    fn path(&self, route: &str) -> PathBuf {
+        let output = self.tmp.path().join("public");
+        let route = route.trim_start_matches('/');
+        let file = output.join(route);
+        if file.is_dir() || route.is_empty() {
+            file.join("index.html")
+        } else {
+            file
         }
     }
 
-    #[test]
-    fn templates_should_exist() {
-        use crate::context::TEMPLATE_MAP;
-        let templates = vec![
-            "/",
-            "404",
-            "500",
-            "/blog",
-            "/linkedin",
-            "/github",
-            "/resume_pdf",
-            "/resume",
-        ];
-        for template in templates {
-            assert!(TEMPLATE_MAP.contains_key(template))
+    fn read(&self, route: &str) -> String {
+        let path = self.path(route);
+        fs::read_to_string(&path).unwrap_or_else(|e| panic!("cannot read {:?}: {}", path, e))
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let path = entry.unwrap().path();
+        let dest = to.join(path.file_name().unwrap());
+        if path.is_dir() {
+            copy_dir(&path, &dest);
+        } else {
+            fs::copy(&path, dest).unwrap();
         }
     }
+}
 
-    #[test]
-    fn blog_files_should_be_parsable() {
-        use crate::blog::get_org_mode_files;
-        use crate::context::BLOG_ROOT;
-        get_org_mode_files(BLOG_ROOT);
+#[test]
+fn static_pages_should_exist() {
+    let site = TestSite::build();
+    let routes = vec![
+        "/",
+        "/github",
+        "/linkedin",
+        "/resume",
+        "/blog",
+        "/pics",
+        "/404.html",
+        "/500.html",
+        "/robots.txt",
+        "/feed/index.xml",
+        "/dpbriggs_resume.pdf",
+        "/static/favicons/apple-touch-icon.png",
+        "/static/favicons/favicon-32x32.png",
+        "/static/favicons/favicon-16x16.png",
+        "/static/favicons/site.webmanifest",
+        "/static/favicons/safari-pinned-tab.svg",
+        "/static/favicons/favicon.ico",
+        "/static/favicons/browserconfig.xml",
+        "/static/css/site.css",
+        "/static/css/blog.css",
+        "/static/css/solarized-dark.css",
+        "/static/css/solarized-light.css",
+        "/static/css/colors.css",
+        "/static/css/bootstrap-slate.css",
+    ];
+    for route in routes {
+        assert!(site.path(route).is_file(), "{} was not generated", route);
     }
+}
 
-    #[test]
-    fn org_mode_files_should_have_matching_html() {
-        use crate::context::BLOG_ROOT;
-        use std::ffi::OsStr;
-        use std::fs;
-        use std::path::PathBuf;
+#[test]
+fn configured_pages_should_render_without_code_changes() {
+    let config_dir = TempDir::new().unwrap();
+    let config = config_dir.path().join("site.toml");
+    let talks =
+        "\n[[pages]]\nuri = \"/talks\"\ntemplate = \"github.html.tera\"\ntitle = \"talks\"\n";
+    fs::write(&config, fs::read_to_string("site.toml").unwrap() + talks).unwrap();
 
-        let base = PathBuf::from(BLOG_ROOT);
-        if !base.is_dir() {
-            panic!("BLOG_ROOT is not a directory!")
-        }
+    let site = TestSite::build_with(|builder| builder.config(&config));
+    let page = site.read("/talks");
+    assert!(page.contains(r#"<meta property="og:title" content="talks">"#));
+    assert!(page.contains(r#"<link rel="canonical" href="https://dpbriggs.ca/talks/">"#));
+}
+
+#[test]
+fn navbar_should_highlight_the_current_section() {
+    let site = TestSite::build();
+    let article = site.read("/blog/good-blog-file");
+    assert_eq!(article.matches("navbar-underlined").count(), 1);
+    assert!(article.contains(
+        r#"<a class="nav-link" href="/blog"><span class="navbar-underlined">Blog</span></a>"#
+    ));
+    let about = site.read("/about");
+    assert_eq!(about.matches("navbar-underlined").count(), 0);
+}
+
+#[test]
+fn site_templates_and_static_files_should_override_the_theme() {
+    let site_dir = TempDir::new().unwrap();
+    let templates = site_dir.path().join("templates");
+    copy_dir(Path::new("templates"), &templates);
+    fs::write(
+        templates.join("navbar.html.tera"),
+        "<nav>custom navbar</nav>",
+    )
+    .unwrap();
+    let static_dir = site_dir.path().join("static");
+    copy_dir(Path::new("static"), &static_dir);
+    fs::create_dir_all(static_dir.join("css")).unwrap();
+    fs::write(static_dir.join("css/site.css"), "body { color: red; }").unwrap();
+    fs::write(static_dir.join("css/colors.css"), "stale").unwrap();
+
+    let site =
+        TestSite::build_with(|builder| builder.templates_dir(&templates).static_dir(&static_dir));
+    let index = site.read("/");
+    assert!(index.contains("<nav>custom navbar</nav>"));
+    assert!(index.contains(r#"<meta property="og:site_name""#));
+    assert_eq!(site.read("/static/css/site.css"), "body { color: red; }");
+    assert!(site.path("/static/css/blog.css").is_file());
+    // The generated color scheme replaces a static file of the same name.
+    assert_ne!(site.read("/static/css/colors.css"), "stale");
+}
+
+#[test]
+fn archives_should_hold_each_file_once() {
+    let tmp = TempDir::new().unwrap();
+    let static_dir = tmp.path().join("static");
+    copy_dir(Path::new("static"), &static_dir);
+    fs::create_dir_all(static_dir.join("css")).unwrap();
+    fs::write(static_dir.join("css/colors.css"), "stale").unwrap();
+    fs::create_dir(tmp.path().join("blog")).unwrap();
+
+    let tarball = tmp.path().join("site.tar.gz");
+    let mut sink = TarGzSink::new(&tarball);
+    SiteBuilder::new(&mut sink)
+        .blog_root(tmp.path().join("blog"))
+        .static_dir(&static_dir)
+        .build()
+        .unwrap();
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(
+        fs::File::open(&tarball).unwrap(),
+    ));
+    let names: Vec<String> = archive
+        .entries()
+        .unwrap()
+        .map(|e| e.unwrap().path().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(
+        names
+            .iter()
+            .filter(|name| *name == "static/css/colors.css")
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn caller_filters_and_templates_should_be_usable() {
+    let site_dir = TempDir::new().unwrap();
+    let templates = site_dir.path().join("templates");
+    copy_dir(Path::new("templates"), &templates);
+    fs::write(
+        templates.join("navbar.html.tera"),
+        r#"<nav>{{ "custom" | shout }} {% include "caller.html.tera" %}</nav>"#,
+    )
+    .unwrap();
+    let mut tera = Tera::default();
+    tera.register_filter("shout", |value: &tera::Value, _: &_| {
+        Ok(tera::Value::String(
+            value.as_str().unwrap_or_default().to_uppercase(),
+        ))
+    });
+    tera.add_raw_template("caller.html.tera", "from the caller")
+        .unwrap();
+
+    let site = TestSite::build_with(|builder| builder.templates_dir(&templates).tera(tera));
+    assert!(site.read("/").contains("<nav>CUSTOM from the caller</nav>"));
+}
+
+#[test]
+fn unknown_themes_should_fail_the_build() {
+    let config_dir = TempDir::new().unwrap();
+    let config = config_dir.path().join("site.toml");
+    let source = fs::read_to_string("site.toml").unwrap();
+    fs::write(
+        &config,
+        source.replace(r#"theme = "slate""#, r#"theme = "missing""#),
+    )
+    .unwrap();
+
+    let tmp = TempDir::new().unwrap();
+    let mut sink = MemorySink::new();
+    let err = SiteBuilder::new(&mut sink)
+        .config(&config)
+        .blog_root(tmp.path())
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("themes/missing"), "{}", err);
+}
+
+#[test]
+fn pics_named_like_the_preview_card_should_fail_the_build() {
+    let tmp = TempDir::new().unwrap();
+    let session = tmp.path().join("pics/2420-04-20");
+    fs::create_dir_all(&session).unwrap();
+    fs::write(
+        session.join("PIC.md"),
+        "# Cards\n\n## Card\nfile: _card.png\n",
+    )
+    .unwrap();
+    fs::write(session.join("_card.png"), b"not a card").unwrap();
+    fs::create_dir(tmp.path().join("blog")).unwrap();
+
+    let mut sink = MemorySink::new();
+    let err = SiteBuilder::new(&mut sink)
+        .blog_root(tmp.path().join("blog"))
+        .pics_root(tmp.path().join("pics"))
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("_card.png"), "{}", err);
+}
+
+#[test]
+fn pics_named_like_the_thumbnails_should_fail_the_build() {
+    let tmp = TempDir::new().unwrap();
+    let session = tmp.path().join("pics/2420-04-20");
+    fs::create_dir_all(&session).unwrap();
+    fs::write(
+        session.join("PIC.md"),
+        "# Thumbs\n\n## Thumbs\nfile: _thumbs\n",
+    )
+    .unwrap();
+    fs::write(session.join("_thumbs"), b"not a directory").unwrap();
+    fs::create_dir(tmp.path().join("blog")).unwrap();
+
+    let mut sink = MemorySink::new();
+    let err = SiteBuilder::new(&mut sink)
+        .blog_root(tmp.path().join("blog"))
+        .pics_root(tmp.path().join("pics"))
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("_thumbs"), "{}", err);
+}
+
+#[test]
+fn content_pages_should_not_replace_generated_pages() {
+    for name in ["pics.md", "blog.md", "Blog.md"] {
+        let tmp = TempDir::new().unwrap();
+        fs::create_dir(tmp.path().join("blog")).unwrap();
+        fs::create_dir(tmp.path().join("pages")).unwrap();
+        fs::write(tmp.path().join("pages").join(name), "# Mine\n").unwrap();
+
+        let mut sink = MemorySink::new();
+        let err = SiteBuilder::new(&mut sink)
+            .blog_root(tmp.path().join("blog"))
+            .pages_root(tmp.path().join("pages"))
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("index.html"), "{}: {}", name, err);
+    }
+}
+
+#[test]
+fn published_photos_should_not_carry_their_location() {
+    let tmp = TempDir::new().unwrap();
+    let session = tmp.path().join("pics/2420-04-20");
+    fs::create_dir_all(&session).unwrap();
+    fs::write(
+        session.join("PIC.md"),
+        "# Secret\n\n## Home\nfile: home.jpg\ngeo: off\n",
+    )
+    .unwrap();
+    let mut jpeg = std::io::Cursor::new(Vec::new());
+    RgbImage::new(8, 8)
+        .write_to(&mut jpeg, image::ImageFormat::Jpeg)
+        .unwrap();
+    let jpeg = jpeg.into_inner();
+    let xmp = b"http://ns.adobe.com/xap/1.0/\0<x:xmpmeta exif:GPSLatitude=\"43,38N\"/>";
+    let len = (xmp.len() + 2) as u16;
+    let jpeg = [
+        &jpeg[..2],
+        &[0xff, 0xe1],
+        &len.to_be_bytes(),
+        xmp,
+        &jpeg[2..],
+    ]
+    .concat();
+    fs::write(session.join("home.jpg"), jpeg).unwrap();
+    fs::create_dir(tmp.path().join("blog")).unwrap();
+
+    let mut sink = DirSink::new(tmp.path().join("public"));
+    SiteBuilder::new(&mut sink)
+        .blog_root(tmp.path().join("blog"))
+        .pics_root(tmp.path().join("pics"))
+        .build()
+        .unwrap();
+    let published = fs::read(tmp.path().join("public/pics/2420-04-20/home.jpg")).unwrap();
+    assert!(!published.windows(11).any(|w| w == b"GPSLatitude"));
+    image::load_from_memory(&published).unwrap();
+}
+
+#[test]
+fn unknown_pages_should_404() {
+    let site = TestSite::build();
+    for route in ["/adawdawd", "/ada2wdawd/adawdw", "/ada3dawd/afwf/afwafa"] {
+        assert!(!site.path(route).exists(), "{} should not exist", route);
+    }
+    let not_found = site.read("/404.html");
+    assert!(not_found.contains("404"));
+    // 404.html is served for every unknown uri, so it has no canonical url.
+    assert!(!not_found.contains("rel=\"canonical\""));
+    assert!(!not_found.contains("og:url"));
+}
+
+#[test]
+fn five_hundred_page_should_render() {
+    let site = TestSite::build();
+    let page = site.read("/500.html");
+    assert!(page.contains("500"));
+    assert!(page.contains("Something bad happened."));
+}
+
+#[test]
+fn pages_should_link_fingerprinted_assets() {
+    let site = TestSite::build();
+    let index = site.read("/");
+    assert!(!index.contains("href=\"/static/css/site.css\""));
+    let start = index.find("/static/css/site.").expect("site.css is linked");
+    let url = &index[start..index[start..].find('"').unwrap() + start];
+    assert!(site.path(url).is_file(), "{} was not generated", url);
+    assert!(index.contains("integrity=\"sha384-"));
+}
+
+#[test]
+fn good_articles_should_render_and_bad_ones_be_skipped() {
+    let site = TestSite::build();
+    let article = site.read("/blog/good-blog-file");
+    assert!(article.contains("Making an Org Mode Blog in Rust"));
+    assert!(article.contains("\"@type\":\"BlogPosting\""));
+    assert!(
+        article
+            .contains(r#"<link rel="canonical" href="https://dpbriggs.ca/blog/good-blog-file/">"#)
+    );
+    assert!(site.path("/blog/good-blog-file/_card.png").is_file());
+
+    let blog_root = site.read("/blog");
+    assert!(blog_root.contains("/blog/good-blog-file"));
+    for bad in ["missing-date", "missing-title", "missing-toc"] {
+        assert!(!site.path(&format!("/blog/{}", bad)).exists());
+        assert!(!blog_root.contains(&format!("/blog/{}\"", bad)));
+    }
+}
+
+#[test]
+fn content_pages_should_render_with_their_toc() {
+    let site = TestSite::build();
+    let about = site.read("/about");
+    assert!(about.contains(r##"<a href="#background">Background</a>"##));
+    assert!(about.contains(r#"<h3 id="details">Details</h3>"#));
+    assert!(about.contains(r#"<meta property="og:title" content="About">"#));
+    assert!(!about.contains("RSS Feed"));
+
+    let uses = site.read("/uses");
+    assert!(uses.contains(r##"<a href="#org5d6e7f8">2. Software</a>"##));
+    assert!(uses.contains("Obviously."));
+    assert!(
+        uses.contains(r#"<meta name="description" content="A test page exported from org-mode.">"#)
+    );
+}
+
+#[test]
+fn rss_feed_should_list_articles() {
+    let site = TestSite::build();
+    let feed = site.read("/feed/index.xml");
+    assert!(feed.starts_with("<?xml"));
+    assert!(feed.contains("<title>Making an Org Mode Blog in Rust</title>"));
+    assert!(feed.contains("<link>https://dpbriggs.ca/blog/good-blog-file/</link>"));
+    assert!(feed.contains("<pubDate> Wed, 06 Feb 2019 1:01:00 EST </pubDate>"));
+    assert_eq!(feed.matches("<item>").count(), 1);
+}
+
+#[test]
+fn pics_sessions_should_render_and_copy_photos() {
+    let site = TestSite::build();
+    let index = site.read("/pics");
+    assert!(index.contains("Test Roll"));
+    let session = site.read("/pics/2420-04-20");
+    assert!(session.contains("Red square"));
+    assert!(session.contains("\"@type\":\"ImageGallery\""));
+    assert!(site.path("/pics/2420-04-20/red.jpg").is_file());
+    assert!(site.path("/pics/2420-04-20/_card.png").is_file());
+    assert!(!site.path("/pics/2420-04-20/PIC.md").exists());
+}
+
+#[test]
+fn hidden_photos_should_not_be_published() {
+    let site = TestSite::build();
+    assert!(!site.path("/pics/2420-04-20/blue.jpg").exists());
+    assert!(!site.path("/pics/2420-04-20/blue").exists());
+    assert!(!site.read("/pics/2420-04-20").contains("blue.jpg"));
+    assert!(!site.path("/pics/2420-04-20/_thumbs/blue.jpg").exists());
+}
+
+#[test]
+fn pics_index_should_show_thumbnails() {
+    let site = TestSite::build();
+    let index = site.read("/pics");
+    assert!(index.contains("src=\"/pics/2420-04-20/_thumbs/red.jpg\""));
+    assert!(!index.contains("src=\"/pics/2420-04-20/red.jpg\""));
+    let thumbnail = image::open(site.path("/pics/2420-04-20/_thumbs/red.jpg")).unwrap();
+    assert_eq!((thumbnail.width(), thumbnail.height()), (32, 32));
+    // Photos which can't be read have no thumbnail, so the index shows the photo.
+    assert!(!site.path("/pics/2420-04-20/_thumbs/missing.jpg").exists());
+    let geojson = site.read("/pics/map/photos.geojson");
+    assert!(geojson.contains("\"image\":\"/pics/2420-04-20/_thumbs/red.jpg\""));
+}
+
+#[test]
+fn sessions_on_the_same_day_should_get_their_own_pages() {
+    let site = TestSite::build();
+    let harbour = site.read("/pics/2420-04-20-harbour");
+    assert!(harbour.contains("A second outing the same day."));
+    let index = site.read("/pics");
+    assert!(index.contains("href=\"/pics/2420-04-20/\""));
+    assert!(index.contains("href=\"/pics/2420-04-20-harbour/\""));
+}
+
+#[test]
+fn pics_index_should_link_year_archives() {
+    let site = TestSite::build();
+    let index = site.read("/pics");
+    assert!(index.contains("href=\"/pics/2420/\""));
+    assert!(!index.contains("rel=\"next\""));
+    let year = site.read("/pics/2420");
+    assert!(year.contains("/ pics 2420"));
+    assert!(year.contains("href=\"/pics/2420-04-20/red/\""));
+}
+
+#[test]
+fn photos_should_get_their_own_pages() {
+    let site = TestSite::build();
+    let red = site.read("/pics/2420-04-20/red");
+    assert!(red.contains("alt=\"A flat red square\""));
+    assert!(red.contains("\"@type\":\"Photograph\""));
+    assert!(red.contains("rel=\"next\""));
+    assert!(!red.contains("rel=\"prev\""));
+    let missing = site.read("/pics/2420-04-20/missing");
+    assert!(missing.contains("href=\"/pics/2420-04-20/red/\" rel=\"prev\""));
+}
+
+#[test]
+fn tags_should_collect_photos_into_albums() {
+    let site = TestSite::build();
+    let tags = site.read("/pics/tags");
+    for album in ["colour", "red", "square", "test"] {
+        assert!(
+            tags.contains(&format!("href=\"/pics/tags/{}/\"", album)),
+            "{}",
+            album
+        );
+    }
+    let red = site.read("/pics/tags/red");
+    assert!(red.contains("href=\"/pics/2420-04-20/red/\""));
+    assert!(!red.contains("href=\"/pics/2420-04-20/missing/\""));
+    // Session tags apply to every photo in the session.
+    let test = site.read("/pics/tags/test");
+    assert!(test.contains("href=\"/pics/2420-04-20/missing/\""));
+    assert!(
+        site.read("/pics/2420-04-20")
+            .contains("href=\"/pics/tags/colour/\"")
+    );
+}
+
+#[test]
+fn geotagged_photos_should_be_on_the_map() {
+    let site = TestSite::build();
+    let map = site.read("/pics/map");
+    assert!(map.contains("data-geojson=\"/pics/map/photos.geojson\""));
+    assert!(map.contains("/static/js/photo-map."));
+    // The tiles and their attribution come from the [map] table of site.toml.
+    assert!(map.contains("data-tiles=\"https://tile.openstreetmap.org/{z}/{x}/{y}.png\""));
+    assert!(map.contains("<div class=\"photo-map-attribution\">&copy; <a href=\"https://www.openstreetmap.org/copyright\">"));
+    let geojson: serde_json::Value =
+        serde_json::from_str(&site.read("/pics/map/photos.geojson")).unwrap();
+    let features = geojson["features"].as_array().unwrap();
+    assert_eq!(features.len(), 1);
+    assert_eq!(features[0]["properties"]["url"], "/pics/2420-04-20/red/");
+}
+
+#[test]
+fn minify_and_precompress_should_write_compressed_siblings() {
+    let cache = TempDir::new().unwrap();
+    let site = TestSite::build_with(|builder| {
+        builder
+            .minify(true)
+            .precompress(cache.path().join("precompress-cache"))
+    });
+    let article = site.read("/blog/good-blog-file");
+    assert!(article.starts_with("<!doctype html><html lang=en>"));
+    assert!(site.path("/blog/good-blog-file/index.html.gz").is_file());
+    assert!(site.path("/blog/good-blog-file/index.html.br").is_file());
+    assert!(!site.path("/robots.txt.gz").exists());
+}
 
-        for entry in fs::read_dir(base).unwrap() {
-            let entry = entry.unwrap();
-            let path = entry.path();
-            if !path.is_dir() {
+#[test]
+fn memory_sink_should_receive_the_same_files_as_the_output_directory() {
+    let site = TestSite::build();
+    let mut sink = MemorySink::new();
+    SiteBuilder::new(&mut sink)
+        .blog_root(site.tmp.path().join("blog"))
+        .pages_root(site.tmp.path().join("pages"))
+        .pics_root(site.tmp.path().join("pics"))
+        .extra_path("resume/dpbriggs_resume.pdf")
+        .build()
+        .unwrap();
+
+    let output = site.tmp.path().join("public");
+    let on_disk: Vec<PathBuf> = walk_files(&output)
+        .unwrap()
+        .into_iter()
+        .map(|path| path.strip_prefix(&output).unwrap().to_path_buf())
+        .collect();
+    let in_memory: Vec<PathBuf> = sink.files.keys().cloned().collect();
+    assert_eq!(on_disk, in_memory);
+    assert_eq!(
+        sink.get("blog/good-blog-file/index.html"),
+        Some(site.read("/blog/good-blog-file").as_bytes())
+    );
+}
+
+#[test]
+fn base_context_should_contain_necessary_keys() {
+    use crate::blog::OrgBlog;
+    use crate::config::SiteConfig;
+    use crate::context::get_base_context;
+    let blog = OrgBlog {
+        html: Default::default(),
+        blog_files: vec![],
+    };
+    let base_context = get_base_context("/", &blog, &SiteConfig::default()).base;
+    let necessary_keys = vec![
+        "domain_name",
+        "root_uri",
+        "blog_uri",
+        "rss_uri",
+        "resume_uri",
+        "resume_pdf_uri",
+        "crash_uri",
+        "web_sep",
+        "admin_email",
+        "full_name",
+        "internet_handle",
+        "my_email",
+        "pics_uri",
+        "github_url",
+        "github_repo_url",
+        "linkedin_url",
+    ];
+    for key in necessary_keys {
+        assert!(base_context.contains_key(key), "missing key {}", key)
+    }
+}
+
+#[test]
+fn templates_should_exist() {
+    use crate::theme::load_templates;
+    let tera = load_templates(&["themes/slate/templates".into(), "templates".into()]).unwrap();
+    let names: Vec<&str> = tera.get_template_names().collect();
+    let templates = vec![
+        "index.html.tera",
+        "404.html.tera",
+        "500.html.tera",
+        "blog/blog_root.html.tera",
+        "blog/blog_article.html.tera",
+        "blog-rss.xml.tera",
+        "linkedin.html.tera",
+        "github.html.tera",
+        "resume.html.tera",
+        "pics.html.tera",
+        "pics/pic_session.html.tera",
+        "pics/pic_photo.html.tera",
+        "pics/tags.html.tera",
+        "pics/album.html.tera",
+        "pics/map.html.tera",
+        "page.html.tera",
+    ];
+    for template in templates {
+        assert!(names.contains(&template), "missing template {}", template)
+    }
+}
+
+#[test]
+fn blog_files_should_be_parsable() {
+    use crate::blog::get_org_mode_files;
+    use crate::context::BLOG_ROOT;
+    let files = get_org_mode_files(BLOG_ROOT).unwrap();
+    let html_files = fs::read_dir(BLOG_ROOT)
+        .unwrap()
+        .flat_map(|dir| fs::read_dir(dir.unwrap().path()).unwrap())
+        .filter(|file| file.as_ref().unwrap().path().extension() == Some("html".as_ref()))
+        .count();
+    assert_eq!(files.len(), html_files);
+}
+
+#[test]
+fn org_mode_files_should_have_matching_html() {
+    use crate::context::BLOG_ROOT;
+    use std::ffi::OsStr;
+
+    let base = PathBuf::from(BLOG_ROOT);
+    if !base.is_dir() {
+        panic!("BLOG_ROOT is not a directory!")
+    }
+
+    for entry in fs::read_dir(base).unwrap() {
+        let entry = entry.unwrap();
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let mut num_org = 0;
+        let mut num_html = 0;
+        for file in fs::read_dir(path).unwrap() {
+            let file = file.unwrap();
+            let path = file.path();
+            if path.is_dir() {
                 continue;
             }
-            let mut num_org = 0;
-            let mut num_html = 0;
-            for file in fs::read_dir(path).unwrap() {
-                let file = file.unwrap();
-                let path = file.path();
-                if path.is_dir() {
-                    continue;
-                }
-                let extension = path.extension().and_then(OsStr::to_str).unwrap_or("");
-                match extension {
-                    "html" => num_html += 1,
-                    "org" => num_org += 1,
-                    _ => println!("Unknown file extensions {} found!", extension),
-                }
+            let extension = path.extension().and_then(OsStr::to_str).unwrap_or("");
+            match extension {
+                "html" => num_html += 1,
+                "org" => num_org += 1,
+                _ => println!("Unknown file extensions {} found!", extension),
             }
-            assert_eq!(num_html, num_org);
         }
+        assert_eq!(num_html, num_org);
     }
+}
 
-    #[test]
-    fn org_parser_should_parse_good_files() {
-        use crate::blog::get_html_contents;
-        use std::path::PathBuf;
-        let test_blog_path = PathBuf::from("tests/good-blog-files/2420-04-20/good-blog-file.html");
-        let res = get_html_contents(&test_blog_path);
-        dbg!(&res);
-        assert!(res.is_ok());
-    }
+#[test]
+fn org_parser_should_parse_good_files() {
+    use crate::blog::get_html_contents;
+    let test_blog_path = PathBuf::from("tests/good-blog-files/2420-04-20/good-blog-file.html");
+    let res = get_html_contents(&test_blog_path);
+    dbg!(&res);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn org_parser_should_throw_applicable_errors() {
+    use crate::blog::{ParsingError, get_html_contents};
+    use crate::error::SiteError;
 
-    #[test]
-    fn org_parser_should_throw_applicable_errors() {
-        use crate::blog::{ParsingError, get_html_contents};
-        use std::path::PathBuf;
-        let missing_date_loc = "tests/bad-org-mode-files/missing-date.html";
-        let missing_date = PathBuf::from(missing_date_loc);
-        let missing_date_err = ParsingError::CannotFindDate(missing_date.clone());
+    let expected = [
+        (
+            "missing-date.html",
+            ParsingError::CannotFindDate as fn(PathBuf) -> ParsingError,
+        ),
+        ("missing-title.html", ParsingError::CannotFindTitle),
+        ("missing-toc.html", ParsingError::CannotFindToc),
+    ];
+    for (file, err) in expected {
+        let path = Path::new(BAD_ORG_MODE_FILES).join(file);
+        match get_html_contents(&path) {
+            Ok(_) => panic!("Successfully parsed bad file {:?}", path),
+            Err(e) => assert_eq!(e.downcast_ref::<ParsingError>(), Some(&err(path))),
+        }
+    }
 
-        let bad_html_loc = "tests/bad-org-mode-files/non-existent.html";
-        let bad_html = PathBuf::from(bad_html_loc);
-        let bad_html_err = ParsingError::CannotParseHtml(bad_html.clone());
+    let missing = Path::new(BAD_ORG_MODE_FILES).join("non-existent.html");
+    match get_html_contents(&missing) {
+        Ok(_) => panic!("Successfully parsed missing file {:?}", missing),
+        Err(e) => assert!(matches!(
+            e.downcast_ref::<SiteError>(),
+            Some(SiteError::IoError(_))
+        )),
+    }
+}
 
-        let missing_title_loc = "tests/bad-org-mode-files/missing-title.html";
-        let missing_title = PathBuf::from(missing_title_loc);
-        let missing_title_err = ParsingError::CannotFindTitle(missing_title.clone());
+/// SNAPSHOT_DIR holds the expected output of every page type, rendered from
+/// the fixtures. Run the tests with `UPDATE_SNAPSHOTS=1` to accept changes.
+static SNAPSHOT_DIR: &str = "tests/snapshots";
 
-        let missing_toc_loc = "tests/bad-org-mode-files/missing-toc.html";
-        let missing_toc = PathBuf::from(missing_toc_loc);
-        let missing_toc_err = ParsingError::CannotFindToc(missing_toc.clone());
+#[test]
+fn rendered_pages_should_match_snapshots() {
+    use similar::TextDiff;
 
-        match get_html_contents(&missing_date) {
-            Ok(_) => panic!("Successfully parsed bad file {:?}", missing_date_loc),
-            Err(e) => assert_eq!(e, missing_date_err),
-        }
-        match get_html_contents(&bad_html) {
-            Ok(_) => panic!("Successfully parsed bad file {:?}", bad_html_loc),
-            Err(e) => assert_eq!(e, bad_html_err),
-        }
-        match get_html_contents(&missing_title) {
-            Ok(_) => panic!("Successfully parsed bad file {:?}", missing_title_loc),
-            Err(e) => assert_eq!(e, missing_title_err),
+    let site = TestSite::build();
+    let pages = [
+        ("index.html", "/"),
+        ("blog_root.html", "/blog"),
+        ("blog_article.html", "/blog/good-blog-file"),
+        ("pics.html", "/pics"),
+        ("pic_session.html", "/pics/2420-04-20"),
+        ("pic_photo.html", "/pics/2420-04-20/red"),
+        ("pics_album.html", "/pics/tags/red"),
+        ("page_markdown.html", "/about"),
+        ("page_org.html", "/uses"),
+        ("404.html", "/404.html"),
+        ("500.html", "/500.html"),
+        ("blog-rss.xml", "/feed/index.xml"),
+    ];
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let mut mismatches = Vec::new();
+    for (snapshot, route) in pages {
+        let actual = site.read(route);
+        let path = Path::new(SNAPSHOT_DIR).join(snapshot);
+        if update {
+            fs::create_dir_all(SNAPSHOT_DIR).unwrap();
+            fs::write(&path, &actual).unwrap();
+            continue;
         }
-        match get_html_contents(&missing_toc) {
-            Ok(_) => panic!("Successfully parsed bad file {:?}", missing_toc_loc),
-            Err(e) => assert_eq!(e, missing_toc_err),
+        let expected = fs::read_to_string(&path).unwrap_or_default();
+        if expected != actual {
+            let diff = TextDiff::from_lines(&expected, &actual)
+                .unified_diff()
+                .header(&path.to_string_lossy(), route)
+                .to_string();
+            mismatches.push(diff);
         }
     }
+    assert!(
+        mismatches.is_empty(),
+        "rendered pages differ from their snapshots \
+         (rerun with UPDATE_SNAPSHOTS=1 to accept):\n{}",
+        mismatches.join("\n")
+    );
 }
@@ -0,0 +1,107 @@
+use crate::assets::walk_files;
+use crate::error::SiteError;
+use miette::Result;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tera::Tera;
+
+/// THEMES_DIR is the default relative path to the themes.
+/// A theme is a directory with `templates/` and `static/` subdirectories.
+pub static THEMES_DIR: &str = "themes";
+
+/// TEMPLATES_DIR is the default relative path to the site's own templates.
+pub static TEMPLATES_DIR: &str = "templates";
+
+/// overlay_files returns every file under `dirs` keyed by its path relative to
+/// its directory, with files in later directories replacing those in earlier ones.
+/// Directories which don't exist are skipped.
+pub fn overlay_files(dirs: &[PathBuf]) -> Result<BTreeMap<String, PathBuf>> {
+    let mut files = BTreeMap::new();
+    for dir in dirs.iter().filter(|dir| dir.is_dir()) {
+        for path in walk_files(dir)? {
+            let rel_path = path
+                .strip_prefix(dir)
+                .expect("walk_files only returns children of dir")
+                .to_string_lossy()
+                .replace('\\', "/");
+            files.insert(rel_path, path);
+        }
+    }
+    Ok(files)
+}
+
+/// theme_dir returns the directory of the theme called `name` in `themes_dir`.
+pub fn theme_dir(themes_dir: &Path, name: &str) -> Result<PathBuf> {
+    let dir = themes_dir.join(name);
+    if !dir.is_dir() {
+        return Err(SiteError::NotADirectory(dir).into());
+    }
+    Ok(dir)
+}
+
+/// load_templates loads every `*.tera` template in `dirs`, so templates in later
+/// directories override the ones with the same name in earlier directories,
+/// i.e. a site's `templates/navbar.html.tera` overrides its theme's navbar.
+pub fn load_templates(dirs: &[PathBuf]) -> Result<Tera> {
+    let templates: Vec<(PathBuf, Option<String>)> = overlay_files(dirs)?
+        .into_iter()
+        .filter(|(name, _)| name.ends_with(".tera"))
+        .map(|(name, path)| (path, Some(name)))
+        .collect();
+    let mut tera = Tera::default();
+    tera.add_template_files(templates)
+        .map_err(SiteError::from)?;
+    Ok(tera)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn site_files_override_theme_files() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let theme = tmp.path().join("theme");
+        let site = tmp.path().join("site");
+        for (dir, name, contents) in [
+            (
+                &theme,
+                "base.html.tera",
+                "theme base {% block body %}{% endblock body %}",
+            ),
+            (&theme, "nav/navbar.html.tera", "theme nav"),
+            (&site, "nav/navbar.html.tera", "site nav"),
+            (
+                &site,
+                "index.html.tera",
+                "{% extends \"base.html.tera\" %}{% block body %}{% include \"nav/navbar.html.tera\" %}{% endblock body %}",
+            ),
+            (&site, "notes.txt", "not a template"),
+        ] {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        let files =
+            overlay_files(&[theme.clone(), site.clone(), tmp.path().join("missing")]).unwrap();
+        assert_eq!(
+            files["nav/navbar.html.tera"],
+            site.join("nav/navbar.html.tera")
+        );
+        assert_eq!(files["base.html.tera"], theme.join("base.html.tera"));
+
+        let tera = load_templates(&[theme, site]).unwrap();
+        let mut names: Vec<&str> = tera.get_template_names().collect();
+        names.sort();
+        assert_eq!(
+            names,
+            ["base.html.tera", "index.html.tera", "nav/navbar.html.tera"]
+        );
+        let rendered = tera
+            .render("index.html.tera", &tera::Context::new())
+            .unwrap();
+        assert_eq!(rendered, "theme base site nav");
+    }
+}
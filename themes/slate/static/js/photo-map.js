@@ -0,0 +1,319 @@
+// photo-map.js plots the photos in a GeoJSON FeatureCollection on a map.
+// The features are projected with Web Mercator and drawn over the tiles of
+// the `data-tiles` URL template, if any. The map starts zoomed to fit every
+// photo; it pans by dragging, zooms with the wheel, a double click or its
+// buttons, and redraws when resized. Photos too close to tell apart are
+// clustered into one marker, which zooms in on them when clicked.
+//
+// <div id="photo-map" data-geojson="/pics/map/photos.geojson"
+//      data-tiles="https://tile.openstreetmap.org/{z}/{x}/{y}.png">
+//     <div class="photo-map-attribution">...</div>
+// </div>
+(function () {
+    var TILE_SIZE = 256;
+    var MAX_ZOOM = 18;
+    var FIT_MAX_ZOOM = 16;
+    var PADDING = 40;
+    // CLUSTER_RADIUS is how close, in pixels, markers are merged into a cluster.
+    var CLUSTER_RADIUS = 44;
+    // DRAG_THRESHOLD is how far, in pixels, a pointer moves before it's a drag, not a click.
+    var DRAG_THRESHOLD = 4;
+
+    // project returns the pixel position of lon/lat at zoom in the whole world map.
+    function project(lon, lat, zoom) {
+        var scale = TILE_SIZE * Math.pow(2, zoom);
+        var sin = Math.sin(lat * Math.PI / 180);
+        sin = Math.min(Math.max(sin, -0.9999), 0.9999);
+        return {
+            x: (lon + 180) / 360 * scale,
+            y: (0.5 - Math.log((1 + sin) / (1 - sin)) / (4 * Math.PI)) * scale
+        };
+    }
+
+    // bounds returns the box around points, projected at zoom.
+    function bounds(points, zoom) {
+        var box = { minX: Infinity, minY: Infinity, maxX: -Infinity, maxY: -Infinity };
+        points.forEach(function (p) {
+            var pos = project(p[0], p[1], zoom);
+            box.minX = Math.min(box.minX, pos.x);
+            box.maxX = Math.max(box.maxX, pos.x);
+            box.minY = Math.min(box.minY, pos.y);
+            box.maxY = Math.max(box.maxY, pos.y);
+        });
+        return box;
+    }
+
+    // fitZoom returns the largest zoom showing every point in width x height.
+    function fitZoom(points, width, height) {
+        for (var zoom = FIT_MAX_ZOOM; zoom > 0; zoom--) {
+            var box = bounds(points, zoom);
+            if (box.maxX - box.minX <= width - 2 * PADDING &&
+                box.maxY - box.minY <= height - 2 * PADDING) {
+                return zoom;
+            }
+        }
+        return 0;
+    }
+
+    function tileUrl(template, zoom, x, y) {
+        return template.replace("{z}", zoom).replace("{x}", x).replace("{y}", y);
+    }
+
+    // cluster groups the features whose markers would overlap at zoom,
+    // each cluster positioned at its first feature.
+    function cluster(features, zoom) {
+        var clusters = [];
+        features.forEach(function (feature) {
+            var c = feature.geometry.coordinates;
+            var pos = project(c[0], c[1], zoom);
+            var near = clusters.find(function (cl) {
+                return Math.abs(cl.x - pos.x) < CLUSTER_RADIUS && Math.abs(cl.y - pos.y) < CLUSTER_RADIUS;
+            });
+            if (near) {
+                near.features.push(feature);
+            } else {
+                clusters.push({ x: pos.x, y: pos.y, features: [feature] });
+            }
+        });
+        return clusters;
+    }
+
+    function PhotoMap(el, features) {
+        this.el = el;
+        this.features = features;
+        this.tiles = el.getAttribute("data-tiles");
+        this.tileImages = {};
+
+        this.pane = document.createElement("div");
+        this.pane.className = "photo-map-pane";
+        el.insertBefore(this.pane, el.firstChild);
+        this.markers = document.createElement("div");
+        this.markers.className = "photo-map-markers";
+        el.insertBefore(this.markers, this.pane.nextSibling);
+        this.addControls();
+
+        var points = features.map(function (f) { return f.geometry.coordinates; });
+        this.zoom = fitZoom(points, el.clientWidth, el.clientHeight);
+        var box = bounds(points, this.zoom);
+        this.center = { x: (box.minX + box.maxX) / 2, y: (box.minY + box.maxY) / 2 };
+
+        this.listen();
+        this.draw();
+    }
+
+    PhotoMap.prototype.addControls = function () {
+        var self = this;
+        var controls = document.createElement("div");
+        controls.className = "photo-map-controls";
+        [["+", "Zoom in", 1], ["−", "Zoom out", -1]].forEach(function (control) {
+            var button = document.createElement("button");
+            button.type = "button";
+            button.textContent = control[0];
+            button.title = control[1];
+            button.setAttribute("aria-label", control[1]);
+            button.addEventListener("click", function () {
+                self.zoomBy(control[2], self.el.clientWidth / 2, self.el.clientHeight / 2);
+            });
+            controls.appendChild(button);
+        });
+        this.el.appendChild(controls);
+    };
+
+    // origin returns the world pixel at the top left corner of the map.
+    PhotoMap.prototype.origin = function () {
+        return {
+            x: this.center.x - this.el.clientWidth / 2,
+            y: this.center.y - this.el.clientHeight / 2
+        };
+    };
+
+    // zoomBy zooms by delta levels, keeping the point at x, y of the map in place.
+    PhotoMap.prototype.zoomBy = function (delta, x, y) {
+        var zoom = Math.min(Math.max(this.zoom + delta, 0), MAX_ZOOM);
+        if (zoom === this.zoom) {
+            return;
+        }
+        var origin = this.origin();
+        var scale = Math.pow(2, zoom - this.zoom);
+        var anchorX = (origin.x + x) * scale;
+        var anchorY = (origin.y + y) * scale;
+        this.zoom = zoom;
+        this.center = {
+            x: anchorX - x + this.el.clientWidth / 2,
+            y: anchorY - y + this.el.clientHeight / 2
+        };
+        this.draw();
+    };
+
+    PhotoMap.prototype.listen = function () {
+        var self = this;
+        var el = this.el;
+        var drag = null;
+        var dragged = false;
+        var frame = null;
+
+        function redraw() {
+            if (frame === null) {
+                frame = requestAnimationFrame(function () {
+                    frame = null;
+                    self.draw();
+                });
+            }
+        }
+
+        el.addEventListener("pointerdown", function (event) {
+            if (event.button !== 0 || event.target.closest(".photo-map-controls, .photo-map-attribution")) {
+                return;
+            }
+            drag = { x: event.clientX, y: event.clientY, center: self.center, id: event.pointerId };
+            dragged = false;
+        });
+        el.addEventListener("pointermove", function (event) {
+            if (!drag || event.pointerId !== drag.id) {
+                return;
+            }
+            var dx = event.clientX - drag.x;
+            var dy = event.clientY - drag.y;
+            if (!dragged && Math.abs(dx) + Math.abs(dy) < DRAG_THRESHOLD) {
+                return;
+            }
+            if (!dragged) {
+                dragged = true;
+                el.setPointerCapture(drag.id);
+                el.classList.add("photo-map-dragging");
+            }
+            self.center = { x: drag.center.x - dx, y: drag.center.y - dy };
+            redraw();
+        });
+        function endDrag() {
+            drag = null;
+            el.classList.remove("photo-map-dragging");
+        }
+        el.addEventListener("pointerup", endDrag);
+        el.addEventListener("pointercancel", endDrag);
+        // A drag which ends on a marker isn't a click on it.
+        el.addEventListener("click", function (event) {
+            if (dragged) {
+                event.preventDefault();
+                event.stopPropagation();
+                dragged = false;
+            }
+        }, true);
+
+        el.addEventListener("wheel", function (event) {
+            event.preventDefault();
+            var rect = el.getBoundingClientRect();
+            self.zoomBy(event.deltaY < 0 ? 1 : -1, event.clientX - rect.left, event.clientY - rect.top);
+        }, { passive: false });
+        el.addEventListener("dblclick", function (event) {
+            if (event.target.closest(".photo-map-controls")) {
+                return;
+            }
+            var rect = el.getBoundingClientRect();
+            self.zoomBy(1, event.clientX - rect.left, event.clientY - rect.top);
+        });
+
+        if (window.ResizeObserver) {
+            new ResizeObserver(redraw).observe(el);
+        } else {
+            window.addEventListener("resize", redraw);
+        }
+    };
+
+    PhotoMap.prototype.draw = function () {
+        this.drawTiles();
+        this.drawMarkers();
+    };
+
+    // drawTiles shows the tiles covering the map, reusing those already loaded.
+    PhotoMap.prototype.drawTiles = function () {
+        if (!this.tiles) {
+            return;
+        }
+        var width = this.el.clientWidth;
+        var height = this.el.clientHeight;
+        var origin = this.origin();
+        var count = Math.pow(2, this.zoom);
+        var shown = {};
+        for (var ty = Math.floor(origin.y / TILE_SIZE); ty * TILE_SIZE < origin.y + height; ty++) {
+            if (ty < 0 || ty >= count) {
+                continue;
+            }
+            for (var tx = Math.floor(origin.x / TILE_SIZE); tx * TILE_SIZE < origin.x + width; tx++) {
+                var key = this.zoom + "/" + tx + "/" + ty;
+                var img = this.tileImages[key];
+                if (!img) {
+                    img = document.createElement("img");
+                    img.className = "photo-map-tile";
+                    img.alt = "";
+                    img.draggable = false;
+                    img.src = tileUrl(this.tiles, this.zoom, ((tx % count) + count) % count, ty);
+                    this.pane.appendChild(img);
+                    this.tileImages[key] = img;
+                }
+                img.style.left = (tx * TILE_SIZE - origin.x) + "px";
+                img.style.top = (ty * TILE_SIZE - origin.y) + "px";
+                shown[key] = true;
+            }
+        }
+        for (var old in this.tileImages) {
+            if (!shown[old]) {
+                this.pane.removeChild(this.tileImages[old]);
+                delete this.tileImages[old];
+            }
+        }
+    };
+
+    // drawMarkers shows a marker per photo, or per cluster of nearby photos.
+    PhotoMap.prototype.drawMarkers = function () {
+        var self = this;
+        var origin = this.origin();
+        this.markers.textContent = "";
+        cluster(this.features, this.zoom).forEach(function (cl) {
+            var first = cl.features[0].properties;
+            var marker = document.createElement("a");
+            marker.className = "photo-map-marker";
+            marker.style.left = (cl.x - origin.x) + "px";
+            marker.style.top = (cl.y - origin.y) + "px";
+            if (cl.features.length === 1) {
+                marker.href = first.url;
+                marker.title = first.title + " (" + first.session + ", " + first.date + ")";
+            } else {
+                marker.href = "#";
+                marker.title = cl.features.length + " photos, click to zoom in";
+                marker.addEventListener("click", function (event) {
+                    event.preventDefault();
+                    self.zoomBy(self.zoom < MAX_ZOOM - 1 ? 2 : 1, cl.x - origin.x, cl.y - origin.y);
+                });
+                var count = document.createElement("span");
+                count.className = "photo-map-count";
+                count.textContent = cl.features.length;
+                marker.appendChild(count);
+            }
+            var thumb = document.createElement("img");
+            thumb.src = first.image;
+            thumb.alt = first.title;
+            thumb.loading = "lazy";
+            thumb.draggable = false;
+            marker.insertBefore(thumb, marker.firstChild);
+            self.markers.appendChild(marker);
+        });
+    };
+
+    var el = document.getElementById("photo-map");
+    if (!el) {
+        return;
+    }
+    fetch(el.getAttribute("data-geojson"))
+        .then(function (response) { return response.json(); })
+        .then(function (geojson) {
+            if (geojson.features.length === 0) {
+                var empty = document.createElement("p");
+                empty.className = "photo-map-empty";
+                empty.textContent = "No geotagged photos yet.";
+                el.appendChild(empty);
+                return;
+            }
+            new PhotoMap(el, geojson.features);
+        });
+})();